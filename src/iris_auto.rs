@@ -0,0 +1,244 @@
+use std::fmt;
+use egui::{Pos2, Vec2};
+use image::{DynamicImage, GenericImageView, Pixel};
+
+use super::AvarageingSystem;
+use super::{iris_color, iris_scan};
+
+// long side of the thumbnail the sizes are tried on
+const SWEEP_SIZE:u32 = 128;
+// pixels compared with each other for the silhouette score
+const SILHOUETTE_SAMPLES:usize = 400;
+// the sweep stops after the first palette bigger than this
+const MAX_COLORS:usize = 64;
+
+#[derive(Debug,PartialEq,Clone,Copy,Default)]
+pub enum AutoCriterion {
+    #[default]
+    Elbow,
+    Silhouette,
+    TargetDeltaE,
+}
+
+impl fmt::Display for AutoCriterion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Elbow => write!(f,"Elbow"),
+            Self::Silhouette => write!(f,"Silhouette"),
+            Self::TargetDeltaE => write!(f,"Target ΔE"),
+        }
+    }
+}
+
+#[derive(Debug,Clone,Copy)]
+pub struct AutoPoint {
+    // the settings that produced this palette size
+    pub settings:iris_scan::ScanSettings,
+    pub colors:usize,
+    // mean OkLab distance of the pixels to their color
+    pub mean_delta_e:f32,
+    pub silhouette:Option<f32>,
+}
+
+#[derive(Debug,Clone,Default)]
+pub struct AutoResult {
+    // ordered by palette size
+    pub points:Vec<AutoPoint>,
+    pub best:usize,
+}
+
+impl AutoResult {
+    pub fn best_settings(&self) -> Option<iris_scan::ScanSettings> {
+        self.points.get(self.best).map(|p| p.settings)
+    }
+}
+
+// coarse and fine end of useful delta E gradations
+pub fn gradation_range(color_dist_type:iris_color::ColorSpace) -> (f32,f32) {
+    match color_dist_type {
+        iris_color::ColorSpace::OkLab => (0.5,0.02),
+        iris_color::ColorSpace::CieLab => (50.0,2.0),
+        iris_color::ColorSpace::Rgb => (128.0,5.0),
+    }
+}
+
+// the quantizer parameter stepped from few to many colors
+fn sweep_settings(settings:&iris_scan::ScanSettings,max_steps:u32) -> Vec<iris_scan::ScanSettings> {
+    let geometric = |from:f32,to:f32,step:u32| from * (to / from).powf(step as f32 / (max_steps - 1).max(1) as f32);
+    (0..max_steps).filter_map(|step| {
+        let mut s = *settings;
+        match settings.avaraging_system {
+            AvarageingSystem::MedianCuttin => s.median_cut_amount = step,
+            AvarageingSystem::DeltaE => {
+                let (coarse,fine) = gradation_range(settings.color_dist_type);
+                s.color_gradation = geometric(coarse,fine,step);
+            }
+            AvarageingSystem::MeanShift => s.mean_schift_radius = geometric(50.0,2.0,step),
+            AvarageingSystem::MedianColor => return None,
+        }
+        Some(s)
+    }).collect()
+}
+
+pub fn supports(avaraging_system:AvarageingSystem) -> bool {
+    avaraging_system != AvarageingSystem::MedianColor
+}
+
+// mean distance of every pixel to its color, and the silhouette of a sample of pixels, both in OkLab
+pub fn evaluate(image:&DynamicImage,scan:&iris_scan::ColorScan) -> (f32,Option<f32>) {
+    let palette:std::collections::HashMap<u32,iris_color::OkLab> = scan.color_list.iter().map(|(id,c)| (*id,iris_color::OkLab::from_rgb(&c.to_rgb()))).collect();
+    let mut pixels:Vec<(iris_color::OkLab,u32)> = vec![];
+    let mut sum = 0.0_f64;
+    for ((_,_,rgba),label) in image.pixels().zip(scan.labels.iter()) {
+        if let Some(color) = palette.get(label) {
+            let lab = iris_color::OkLab::from_rgb(&rgba.to_rgb());
+            sum += lab.distance_to_lab(color) as f64;
+            pixels.push((lab,*label));
+        }
+    }
+    let mean = if pixels.is_empty() {0.0} else {(sum / pixels.len() as f64) as f32};
+    if palette.len() < 2 {
+        return (mean,None);
+    }
+    let stride = pixels.len().div_ceil(SILHOUETTE_SAMPLES).max(1);
+    let samples:Vec<&(iris_color::OkLab,u32)> = pixels.iter().step_by(stride).collect();
+    let mut silhouette = 0.0;
+    for (lab,label) in samples.iter() {
+        let mut distances:std::collections::HashMap<u32,(f32,u32)> = std::collections::HashMap::new();
+        for (other,other_label) in samples.iter() {
+            let entry = distances.entry(*other_label).or_insert((0.0,0));
+            entry.0 += lab.distance_to_lab(other);
+            entry.1 += 1;
+        }
+        // the pixel itself is in its own cluster with distance 0
+        let own = distances.get(label).map(|(d,n)| if *n > 1 {d / (*n - 1) as f32} else {0.0}).unwrap_or(0.0);
+        let nearest = distances.iter().filter(|(l,_)| *l != label).map(|(_,(d,n))| d / *n as f32).fold(f32::MAX,f32::min);
+        if nearest < f32::MAX && own.max(nearest) > 0.0 {
+            silhouette += (nearest - own) / own.max(nearest);
+        }
+    }
+    (mean,Some(silhouette / samples.len().max(1) as f32))
+}
+
+pub fn thumbnail(image:&DynamicImage) -> DynamicImage {
+    if image.width().max(image.height()) > SWEEP_SIZE {image.thumbnail(SWEEP_SIZE,SWEEP_SIZE)} else {image.clone()}
+}
+
+// runs the quantizer for growing palette sizes on a thumbnail, one size per update, and picks one
+pub struct AutoSearch {
+    image:DynamicImage,
+    sweep:Vec<iris_scan::ScanSettings>,
+    next:usize,
+    points:Vec<AutoPoint>,
+    criterion:AutoCriterion,
+    target_delta_e:f32,
+}
+
+impl AutoSearch {
+    pub fn new(image:&DynamicImage,settings:&iris_scan::ScanSettings,max_steps:u32,criterion:AutoCriterion,target_delta_e:f32) -> Self {
+        Self{
+            image:thumbnail(image),
+            sweep:sweep_settings(settings,max_steps),
+            next:0,
+            points:vec![],
+            criterion,
+            target_delta_e,
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.next >= self.sweep.len()
+    }
+
+    pub fn progress(&self) -> f32 {
+        self.next as f32 / self.sweep.len().max(1) as f32
+    }
+
+    // scans the next size, returns false once the search is done
+    pub fn step(&mut self) -> bool {
+        if self.is_done() {
+            return false;
+        }
+        let settings = self.sweep[self.next];
+        self.next += 1;
+        let scan = iris_scan::scan(&self.image,&settings);
+        let colors = scan.color_list.len();
+        // settings that give the same size as an earlier step add nothing to the curve
+        if colors == 0 || self.points.iter().any(|p| p.colors == colors) {
+            return !self.is_done();
+        }
+        let (mean_delta_e,silhouette) = evaluate(&self.image,&scan);
+        self.points.push(AutoPoint{settings,colors,mean_delta_e,silhouette});
+        if colors > MAX_COLORS {
+            self.next = self.sweep.len();
+        }
+        !self.is_done()
+    }
+
+    pub fn result(&self) -> AutoResult {
+        let mut points = self.points.clone();
+        points.sort_by_key(|p| p.colors);
+        let best = match self.criterion {
+            AutoCriterion::Elbow => elbow(&points),
+            AutoCriterion::Silhouette => points.iter().enumerate()
+                .filter_map(|(i,p)| p.silhouette.map(|s| (i,s)))
+                .max_by(|a,b| a.1.total_cmp(&b.1))
+                .map(|(i,_)| i).unwrap_or(0),
+            AutoCriterion::TargetDeltaE => points.iter().position(|p| p.mean_delta_e <= self.target_delta_e).unwrap_or(points.len().saturating_sub(1)),
+        };
+        AutoResult{points,best}
+    }
+}
+
+// the point furthest below the line from the first to the last point of the normalized curve
+fn elbow(points:&[AutoPoint]) -> usize {
+    if points.len() < 3 {
+        return points.len().saturating_sub(1);
+    }
+    let (first,last) = (&points[0],&points[points.len() - 1]);
+    let k_range = (last.colors - first.colors).max(1) as f32;
+    let e_range = (first.mean_delta_e - last.mean_delta_e).abs().max(f32::EPSILON);
+    points.iter().enumerate().max_by(|a,b| {
+        let distance = |p:&AutoPoint| {
+            let x = (p.colors - first.colors) as f32 / k_range;
+            let y = (first.mean_delta_e - p.mean_delta_e) / e_range;
+            y - x
+        };
+        distance(a.1).total_cmp(&distance(b.1))
+    }).map(|(i,_)| i).unwrap_or(0)
+}
+
+// error over palette size, the chosen size is highlighted
+pub fn plot(ui:&mut egui::Ui,result:&AutoResult){
+    let (rect,response) = ui.allocate_exact_size(Vec2::new(ui.available_width().clamp(120.0,260.0),100.0),egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    let visuals = ui.visuals();
+    painter.rect_filled(rect,2.0,visuals.extreme_bg_color);
+    let Some(last) = result.points.last() else {
+        return;
+    };
+    let inner = rect.shrink(6.0);
+    let first = &result.points[0];
+    let max_k = last.colors.max(first.colors + 1) as f32;
+    let min_k = first.colors as f32;
+    let max_e = result.points.iter().map(|p| p.mean_delta_e).fold(f32::EPSILON,f32::max);
+    let to_screen = |p:&AutoPoint| Pos2::new(
+        inner.left() + (p.colors as f32 - min_k) / (max_k - min_k) * inner.width(),
+        inner.bottom() - p.mean_delta_e / max_e * inner.height(),
+    );
+    let line:Vec<Pos2> = result.points.iter().map(to_screen).collect();
+    painter.add(egui::Shape::line(line.clone(),egui::Stroke::new(1.5,visuals.text_color())));
+    for (i,pos) in line.iter().enumerate() {
+        let color = if i == result.best {visuals.selection.bg_fill} else {visuals.weak_text_color()};
+        painter.circle_filled(*pos,if i == result.best {4.0} else {2.0},color);
+    }
+    painter.text(rect.left_top() + Vec2::new(4.0,2.0),egui::Align2::LEFT_TOP,format!("ΔE {:.3}",max_e),egui::FontId::monospace(9.0),visuals.weak_text_color());
+    painter.text(rect.right_bottom() - Vec2::new(4.0,2.0),egui::Align2::RIGHT_BOTTOM,format!("{} colors",last.colors),egui::FontId::monospace(9.0),visuals.weak_text_color());
+    // values of the closest point under the cursor
+    if let Some(pointer) = response.hover_pos() && let Some(p) = result.points.iter().min_by(|a,b| {
+        (to_screen(a).x - pointer.x).abs().total_cmp(&(to_screen(b).x - pointer.x).abs())
+    }) {
+        let silhouette = p.silhouette.map(|s| format!("{:.3}",s)).unwrap_or("-".to_string());
+        response.on_hover_text(format!("{} colors\nmean ΔE {:.4}\nsilhouette {}",p.colors,p.mean_delta_e,silhouette));
+    }
+}
@@ -0,0 +1,168 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use image::{DynamicImage, GenericImageView, Pixel, Rgb};
+use serde::{Deserialize, Serialize};
+
+use super::iris_color;
+
+#[derive(Debug,PartialEq,Clone,Copy,Default,Serialize,Deserialize)]
+pub enum BackgroundMode {
+    #[default]
+    Off,
+    // pixels close to the border color that are connected to the border
+    FloodFill,
+    // every pixel close to the border color
+    BorderColor,
+}
+
+impl BackgroundMode {
+    pub const ALL:[BackgroundMode;3] = [Self::Off,Self::FloodFill,Self::BorderColor];
+}
+
+impl fmt::Display for BackgroundMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Off => write!(f,"Off"),
+            Self::FloodFill => write!(f,"Flood fill from border"),
+            Self::BorderColor => write!(f,"Border color"),
+        }
+    }
+}
+
+#[derive(Debug,PartialEq,Clone,Copy,Serialize,Deserialize)]
+pub struct BackgroundSettings {
+    pub mode:BackgroundMode,
+    // OkLab distance to the border color
+    pub tolerance:f32,
+}
+
+impl Default for BackgroundSettings {
+    fn default() -> Self {
+        Self{mode:BackgroundMode::Off,tolerance:0.05}
+    }
+}
+
+// frames are stacked on top of each other, every frame has its own border
+fn on_border(x:u32,y:u32,width:u32,frame_height:u32) -> bool {
+    x == 0 || x + 1 == width || y.is_multiple_of(frame_height) || y % frame_height + 1 == frame_height
+}
+
+// mean of the most common opaque border colors, similar shades are counted together
+pub fn border_color(image:&DynamicImage,frame_height:u32) -> Option<Rgb<u8>> {
+    let (width,height) = image.dimensions();
+    let frame_height = frame_height.clamp(1,height.max(1));
+    let mut buckets:HashMap<[u8;3],([u64;3],u64)> = HashMap::new();
+    // whole rows at the frame edges, only the first and last pixel in between
+    let border = (0..height).flat_map(|y| {
+        let edge_row = y.is_multiple_of(frame_height) || y % frame_height + 1 == frame_height;
+        let step = if edge_row {1} else {width.saturating_sub(1).max(1) as usize};
+        (0..width).step_by(step).map(move |x| (x,y))
+    });
+    for (x,y) in border {
+        let rgba = image.get_pixel(x,y);
+        if rgba[3] == 0 {
+            continue;
+        }
+        let rgb = rgba.to_rgb().0;
+        let (sum,count) = buckets.entry(rgb.map(|c| c >> 3)).or_insert(([0;3],0));
+        for (s,c) in sum.iter_mut().zip(rgb) {
+            *s += c as u64;
+        }
+        *count += 1;
+    }
+    let (sum,count) = buckets.into_values().max_by_key(|(_,count)| *count)?;
+    Some(Rgb(sum.map(|s| (s as f64 / count as f64).round() as u8)))
+}
+
+// one entry per pixel, row by row, set for background. transparent pixels are never background,
+// the flood fill runs through them so transparent padding doesn't stop it
+pub fn detect(image:&DynamicImage,settings:&BackgroundSettings,frame_height:u32) -> Vec<bool> {
+    let (width,height) = image.dimensions();
+    let mut background = vec![false;(width * height) as usize];
+    if settings.mode == BackgroundMode::Off {
+        return background;
+    }
+    let frame_height = frame_height.clamp(1,height.max(1));
+    let Some(reference) = border_color(image,frame_height) else {
+        return background;
+    };
+    let reference = iris_color::OkLab::from_rgb(&reference);
+    let mut cache:HashMap<[u8;3],bool> = HashMap::new();
+    let rgba = image.to_rgba8();
+    let mut close = |x:u32,y:u32| {
+        let p = rgba.get_pixel(x,y).0;
+        *cache.entry([p[0],p[1],p[2]]).or_insert_with(|| iris_color::OkLab::from_rgb(&Rgb([p[0],p[1],p[2]])).distance_to_lab(&reference) <= settings.tolerance)
+    };
+    let opaque = |x:u32,y:u32| rgba.get_pixel(x,y).0[3] > 0;
+    match settings.mode {
+        BackgroundMode::Off => {}
+        BackgroundMode::BorderColor => {
+            for y in 0..height {
+                for x in 0..width {
+                    background[(y * width + x) as usize] = opaque(x,y) && close(x,y);
+                }
+            }
+        }
+        BackgroundMode::FloodFill => {
+            let mut visited = vec![false;(width * height) as usize];
+            let mut queue:VecDeque<(u32,u32)> = VecDeque::new();
+            for y in 0..height {
+                for x in 0..width {
+                    if on_border(x,y,width,frame_height) && (!opaque(x,y) || close(x,y)) {
+                        visited[(y * width + x) as usize] = true;
+                        queue.push_back((x,y));
+                    }
+                }
+            }
+            while let Some((x,y)) = queue.pop_front() {
+                background[(y * width + x) as usize] = opaque(x,y);
+                // neighbours in the same frame
+                let neighbours = [
+                    (x > 0).then(|| (x - 1,y)),
+                    (x + 1 < width).then_some((x + 1,y)),
+                    (y % frame_height > 0).then(|| (x,y - 1)),
+                    (y % frame_height + 1 < frame_height && y + 1 < height).then_some((x,y + 1)),
+                ];
+                for (nx,ny) in neighbours.into_iter().flatten() {
+                    let index = (ny * width + nx) as usize;
+                    if !visited[index] && (!opaque(nx,ny) || close(nx,ny)) {
+                        visited[index] = true;
+                        queue.push_back((nx,ny));
+                    }
+                }
+            }
+        }
+    }
+    background
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // white with a red ring, the white inside the ring isn't connected to the border
+    fn ring_image() -> DynamicImage {
+        let img = image::RgbaImage::from_fn(9,9,|x,y| {
+            match (x,y) {
+                (0,0) => image::Rgba([0,0,0,0]),
+                (4,4) => image::Rgba([250,250,250,255]),
+                (3..6,3..6) => image::Rgba([220,30,30,255]),
+                _ => image::Rgba([250,250,250,255]),
+            }
+        });
+        DynamicImage::ImageRgba8(img)
+    }
+
+    #[test]
+    fn flood_fill_stops_at_the_object() {
+        let image = ring_image();
+        assert_eq!(border_color(&image,9),Some(Rgb([250,250,250])));
+        let flood = detect(&image,&BackgroundSettings{mode:BackgroundMode::FloodFill,tolerance:0.05},9);
+        // everything but the ring, its inside and the transparent corner
+        assert_eq!(flood.iter().filter(|b| **b).count(),81 - 9 - 1);
+        assert!(!flood[4 * 9 + 4]);
+        let border = detect(&image,&BackgroundSettings{mode:BackgroundMode::BorderColor,tolerance:0.05},9);
+        assert_eq!(border.iter().filter(|b| **b).count(),81 - 8 - 1);
+        assert!(border[4 * 9 + 4]);
+    }
+}
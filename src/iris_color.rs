@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::fmt::Debug;
 use nalgebra::{Matrix3, Vector3};
 use std::fmt;
@@ -6,14 +7,13 @@ use egui::ColorImage;
 use egui::Vec2;
 use egui::Widget;
 use image::{Pixel, Rgb};
+use serde::{Deserialize, Serialize};
 
-use super::WINDOW_ID;
-use super::iris_color;
 use super::iris_image_creation as iic;
 
 const OKLAB_TOLERANCE:f32 = 0.01;
 
-#[derive(Debug,PartialEq)]
+#[derive(Debug,PartialEq,Clone,Copy,Serialize,Deserialize)]
 pub enum ColorSpace {
     Rgb,
     CieLab,
@@ -33,6 +33,14 @@ pub fn rgb_distance(col_a:Rgb<u8>,col_b:Rgb<u8>) -> f32{
     dist
 }
 
+pub fn color_distance(col_a:Rgb<u8>,col_b:Rgb<u8>,space:ColorSpace) -> f32 {
+    match space {
+        ColorSpace::Rgb => rgb_distance(col_a,col_b),
+        ColorSpace::CieLab => CieLab::from_rgb(col_a).distance_to_lab(&CieLab::from_rgb(col_b)),
+        ColorSpace::OkLab => OkLab::from_rgb(&col_a).distance_to_lab(&OkLab::from_rgb(&col_b)),
+    }
+}
+
 #[allow(dead_code)]
 pub fn rgb_distance_squared(col_a:Rgb<u8>,col_b:Rgb<u8>) -> f32{
     let r_a = col_a.channels()[0] as f32;
@@ -47,145 +55,148 @@ pub fn rgb_distance_squared(col_a:Rgb<u8>,col_b:Rgb<u8>) -> f32{
     dist
 }
 
-pub fn color_display(ui: &mut egui::Ui,color: &mut AvarageRgb) -> egui::Response {
-    if let Some(texture) = &color.texture {
-        ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP),|ui|{
-            ui.set_width(texture.size()[0] as f32);
-            let img_widget = egui::Image::from_texture(texture); 
-            let img_size = img_widget.size().unwrap();
-            let response = img_widget.sense(egui::Sense::CLICK).ui(ui)
-                .on_hover_text(format!("r:{}|g:{}|b:{}",color.r,color.g,color.b));
-            let min = egui::pos2(response.rect.min.x + img_size[0]/2.0 + 5.0,response.rect.min.y + img_size[1]/2.0);
-            let target = egui::Rect{max:response.rect.max,min};
-            ui.put(target,egui::Checkbox::without_text(&mut color.marked));
-            if response.clicked() {
-                color.color_info_window_open = true;
-            }
-            response.widget_info(|| {
-                egui::WidgetInfo::selected(egui::WidgetType::Image,ui.is_enabled(),color.color_info_window_open,"Display Color")
-            });
-        }).response
-    }else {
-        let (_,response) = ui.allocate_exact_size(egui::vec2(0.0,0.0), egui::Sense::click());
-        response
+// swatch textures by color with the pass they were last drawn in
+#[derive(Clone,Default)]
+struct SwatchCache {
+    pass:u64,
+    textures:HashMap<[u8;3],(egui::TextureHandle,u64)>,
+}
+
+// textures not drawn in the previous pass are freed, so the cache only holds the colors on screen
+pub fn swatch_texture(ctx:&egui::Context,rgb:Rgb<u8>) -> egui::TextureHandle {
+    let id = egui::Id::new("iris_swatches");
+    let pass = ctx.cumulative_pass_nr();
+    let cached = ctx.data_mut(|d| {
+        let cache = d.get_temp_mut_or_default::<SwatchCache>(id);
+        if cache.pass != pass {
+            cache.textures.retain(|_,(_,used)| *used + 1 >= pass);
+            cache.pass = pass;
+        }
+        cache.textures.get_mut(&rgb.0).map(|(texture,used)| {
+            *used = pass;
+            texture.clone()
+        })
+    });
+    if let Some(texture) = cached {
+        return texture;
     }
+    let texture = ctx.load_texture("color_text",ColorImage::new([32,32],Color32::from_rgb(rgb.0[0],rgb.0[1],rgb.0[2])),Default::default());
+    ctx.data_mut(|d| d.get_temp_mut_or_default::<SwatchCache>(id).textures.insert(rgb.0,(texture.clone(),pass)));
+    texture
 }
-pub fn color_display_percent(ui: &mut egui::Ui,color: &mut AvarageRgb,percent:f32) -> egui::Response {
+
+pub fn color_display(ui: &mut egui::Ui,color: &mut PaletteColor,id:egui::Id) -> egui::Response {
+    let texture = swatch_texture(ui.ctx(),color.to_rgb());
+    ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP),|ui|{
+        ui.set_width(texture.size()[0] as f32);
+        let img_widget = egui::Image::from_texture(&texture); 
+        let img_size = img_widget.size().unwrap();
+        let response = img_widget.sense(egui::Sense::CLICK).ui(ui)
+            .on_hover_text(format!("r:{}|g:{}|b:{}",color.r,color.g,color.b));
+        let min = egui::pos2(response.rect.min.x + img_size[0]/2.0 + 5.0,response.rect.min.y + img_size[1]/2.0);
+        let target = egui::Rect{max:response.rect.max,min};
+        ui.put(target,egui::Checkbox::without_text(&mut color.marked));
+        let mut view = ColorView::load(ui.ctx(),id);
+        if response.clicked() {
+            view.info_window_open = true;
+            view.clone().store(ui.ctx(),id);
+        }
+        response.widget_info(|| {
+            egui::WidgetInfo::selected(egui::WidgetType::Image,ui.is_enabled(),view.info_window_open,"Display Color")
+        });
+    }).response
+}
+pub fn color_display_percent(ui: &mut egui::Ui,color: &mut PaletteColor,id:egui::Id,percent:f32) -> egui::Response {
         ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP),|ui|{
-            let response = color_display(ui, color);
+            let response = color_display(ui, color,id);
             ui.label(format!("{}|{}|{} |=> {}%",color.r,color.g,color.b,percent*100.0));
+            let info_window_open = ColorView::load(ui.ctx(),id).info_window_open;
             response.widget_info(|| {
-                egui::WidgetInfo::selected(egui::WidgetType::Image,ui.is_enabled(),color.color_info_window_open,"Display Color plus extra data")
+                egui::WidgetInfo::selected(egui::WidgetType::Image,ui.is_enabled(),info_window_open,"Display Color plus extra data")
             });
             response
         }).response
 }
 
-pub struct AvarageRgb {
+// Per-swatch UI state, kept in egui's temporary memory under the id the swatch is drawn with
+#[derive(Clone,Default)]
+pub struct ColorView {
+    pub info_window_open:bool,
+    pub mark_every_color:bool,
+    generated_for:Option<[u8;3]>,
+    img_rect:Option<egui::TextureHandle>,
+    img_bar:Option<egui::TextureHandle>,
+}
+
+impl ColorView {
+    pub fn load(ctx:&egui::Context,id:egui::Id) -> Self {
+        ctx.data(|d| d.get_temp(id.with("color_view"))).unwrap_or_default()
+    }
+    pub fn store(self,ctx:&egui::Context,id:egui::Id) {
+        ctx.data_mut(|d| d.insert_temp(id.with("color_view"),self));
+    }
+    pub fn generate_color_display(&mut self,ctx:&egui::Context,rgb:Rgb<u8>) {
+        let mut img = iic::HSLRect::new([128,64],HSL::from_rgb(&rgb).h);
+        img.obj.push(iic::RGBMarker::new(rgb,5,2));
+        img.generate_h_bar();
+        img.generate_sl_rect();
+        self.img_bar = Some(ctx.load_texture("img_bar",ColorImage::from_rgb([img.size[0] as usize,(img.size[1]/4) as usize],&img.img_bar),Default::default()));
+        self.img_rect = Some(ctx.load_texture("img_rect",ColorImage::from_rgb([img.size[0] as usize,img.size[1] as usize],&img.img_rect),Default::default()));
+        self.generated_for = Some(rgb.0);
+    }
+}
+
+// where the pixels of a color are in the image, in pixels
+#[derive(Debug,PartialEq,Clone,Copy,Serialize,Deserialize)]
+pub struct SpatialStats {
+    pub centroid:[f32;2],
+    // min x, min y, max x, max y
+    pub bounding_box:[u32;4],
+    // standard deviation along x and y
+    pub spread:[f32;2],
+}
+
+#[derive(Clone,Serialize,Deserialize)]
+pub struct PaletteColor {
     pub r:u8,
     pub g:u8,
     pub b:u8,
     pub color_n:u32,
-    pub texture: Option<egui::TextureHandle>,
-    pub colors:Vec<AvarageRgb>,
-    pub color_info_window_open:bool,
-    pub id:usize,
-    pub img:iic::HSLRect,
-    pub img_rect:Option<egui::TextureHandle>,
-    pub img_bar:Option<egui::TextureHandle>,
-    pub img_dispaly_generated:bool,
+    #[serde(default)]
+    pub colors:Vec<PaletteColor>,
+    #[serde(default)]
     pub marked:bool,
-    pub mark_every_color:bool,
+    #[serde(default)]
     pub position:[u32;2],
+    #[serde(default)]
+    pub spatial:Option<SpatialStats>,
 }
 
-impl Clone for AvarageRgb {
-    fn clone(&self) -> Self {
-        WINDOW_ID.with(|thread_id|{
-            let r = self.r.clone();
-            let g = self.g.clone();
-            let b = self.b.clone();
-            
-            let color_n = self.color_n.clone();
-            let texture = self.texture.clone();
-            let colors = self.colors.clone();
-            let color_info_window_open = false;
-
-            let id = thread_id.get();
-            thread_id.set(id+1);
-
-            let img = self.img.clone();
-            let img_rect = self.img_rect.clone();
-            let img_bar = self.img_bar.clone();
-            let img_dispaly_generated = self.img_dispaly_generated.clone();
-            let marked = self.marked;
-            let position = self.position;
-
-            Self { 
-                r,
-                g,
-                b,
-                color_n,
-                texture,
-                colors,
-                color_info_window_open,
-                id,
-                img,
-                img_rect,
-                img_bar,
-                img_dispaly_generated,
-                marked,
-                mark_every_color:false,
-                position,
-            }
-        })
-    }
-}
-
-impl Debug for AvarageRgb {
+impl Debug for PaletteColor {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
        write!(f,"|r: {}|g: {}|b: {} |=> color_n: {}",self.r,self.g,self.b,self.color_n) 
     }
 }
 
 
-impl AvarageRgb {
+impl PaletteColor {
     pub fn to_rgb(&self) -> Rgb<u8>{
         Rgb::from([self.r,self.g,self.b])
     }
     pub fn from_rgb(rgb:Rgb<u8>,position:[u32;2]) -> Self{
-
-        WINDOW_ID.with(|thread_id|{
-
-            let r = rgb.channels()[0];
-            let g = rgb.channels()[1];
-            let b = rgb.channels()[2];
-
-            let id = thread_id.get();
-            thread_id.set(id+1);
-            let img = iic::HSLRect::new([128,64],iris_color::HSL::from_rgb(&rgb).h);
-            AvarageRgb {
-                r,
-                g,
-                b,
-                color_n:1,
-                texture:None,
-                colors:vec![],
-                color_info_window_open:false,
-                id,
-                img,
-                img_bar: None,
-                img_rect: None,
-                img_dispaly_generated: false,
-                marked: false,
-                mark_every_color:false,
-                position,
-            }
-        })
+        PaletteColor {
+            r:rgb.0[0],
+            g:rgb.0[1],
+            b:rgb.0[2],
+            color_n:1,
+            colors:vec![],
+            marked: false,
+            position,
+            spatial:None,
+        }
     }
-    pub fn switch_to_most_saturated_color(&mut self,ui: &mut egui::Ui){
-        let mut old_main = Self::from_rgb(Rgb::from([self.r,self.g,self.b]),self.position); 
-        old_main.generate_texture(ui);
+    pub fn switch_to_most_saturated_color(&mut self){
+        let old_main = Self::from_rgb(Rgb::from([self.r,self.g,self.b]),self.position); 
         self.colors.push(old_main);  
         self.colors.sort_by(|a,b|{
             let hsl_a = HSL::from_rgb(&a.to_rgb());
@@ -203,23 +214,9 @@ impl AvarageRgb {
         self.r = new_main.r;
         self.g = new_main.g;
         self.b = new_main.b;
-        self.generate_texture(ui);
-        self.generate_color_display();
-
-    }
-    pub fn generate_color_display(&mut self) {
-        let rgb = Rgb::from([self.r,self.g,self.b]);
-        let marker = iic::RGBMarker::new(rgb,5,2);
-        self.img.obj.push(marker);
-        self.img.generate_h_bar();
-        self.img.generate_sl_rect();
-        self.img_dispaly_generated = true;
-    }
-    pub fn generate_texture(&mut self,ui: &mut egui::Ui) {
-        self.texture = Some(ui.ctx().load_texture("color_text",ColorImage::new([32,32],Color32::from_rgb(self.r,self.g,self.b)),Default::default()));
     }
 
-    pub fn _avarage(&mut self,comp: &AvarageRgb){
+    pub fn _avarage(&mut self,comp: &PaletteColor){
         self.color_n += comp.color_n;
         self.r = self.r.checked_add((comp.r as u32/self.color_n.max(1)).try_into().unwrap_or(255_u8)).unwrap_or(255);
         self.g = self.g.checked_add((comp.g as u32/self.color_n.max(1)).try_into().unwrap_or(255_u8)).unwrap_or(255);
@@ -241,100 +238,97 @@ impl AvarageRgb {
         self.g = 254.min(((g + new_g.pow(2))/(self.color_n+1)).isqrt())as u8;
         self.b = 254.min(((b + new_b.pow(2))/(self.color_n+1)).isqrt())as u8;
 
-        let  x = self.position[0] * self.color_n;
-        let  y = self.position[1] * self.color_n;
+        let  x = self.position[0] as u64 * self.color_n as u64;
+        let  y = self.position[1] as u64 * self.color_n as u64;
 
-        self.position[0] = (x + position[0])/(self.color_n+1);
-        self.position[1] = (y + position[0])/(self.color_n+1);
+        self.position[0] = ((x + position[0] as u64)/(self.color_n as u64 + 1)) as u32;
+        self.position[1] = ((y + position[1] as u64)/(self.color_n as u64 + 1)) as u32;
 
-        let difference = self.colors.contains(&AvarageRgb::from_rgb(*comp,position));
+        let difference = self.colors.contains(&PaletteColor::from_rgb(*comp,position));
         if !difference {
-            self.colors.push(AvarageRgb::from_rgb(*comp,position));
+            self.colors.push(PaletteColor::from_rgb(*comp,position));
         }
         self.color_n += 1;
 
     }
-    pub fn color_info_window_show(&mut self,ctx:&egui::Context){
-        if self.color_info_window_open {
-            if self.img_bar.is_none() && self.img_dispaly_generated{
-                self.img_bar = Some(ctx.load_texture("img_bar",ColorImage::from_rgb([self.img.size[0].try_into().unwrap(),(self.img.size[1]/4).try_into().unwrap()],&self.img.img_bar),Default::default()));
-            }
-            if self.img_rect.is_none() && self.img_dispaly_generated{
-                self.img_rect = Some(ctx.load_texture("img_rect",ColorImage::from_rgb([self.img.size[0].try_into().unwrap(),self.img.size[1].try_into().unwrap()],&self.img.img_rect),Default::default()));
+    pub fn color_info_window_show(&mut self,ctx:&egui::Context,id:egui::Id){
+        let mut view = ColorView::load(ctx,id);
+        if !view.info_window_open {
+            return;
+        }
+        let rgb = self.to_rgb();
+        if view.generated_for != Some(rgb.0) {
+            view.generate_color_display(ctx,rgb);
+        }
+        let mut window_open = view.info_window_open;
+        egui::Window::new(format!("{}|{}|{}",self.r,self.g,self.b)).id(id).open(&mut window_open).show(ctx, |ui| {
+            color_display(ui, self,id);
+            if let Some(spatial) = &self.spatial {
+                ui.label(format!("Centroid : {:.1} / {:.1}",spatial.centroid[0],spatial.centroid[1]));
+                let [min_x,min_y,max_x,max_y] = spatial.bounding_box;
+                ui.label(format!("Bounding box : {} / {} to {} / {}",min_x,min_y,max_x,max_y));
+                ui.label(format!("Spread : {:.1} / {:.1}",spatial.spread[0],spatial.spread[1])).on_hover_text("Standard deviation of the pixel positions along x and y");
+            }else{
+                ui.label(format!("Image position : {} / {}",self.position[0],self.position[1]));
             }
-            let mut window_open = self.color_info_window_open;
-            egui::Window::new(format!("{}|{}|{}",self.r,self.g,self.b)).id(egui::Id::new(self.id)).open(&mut window_open).show(ctx, |ui| {
-                color_display(ui, self);
-                if self.img_rect.is_none() && self.img_bar.is_none() {
-                    self.generate_color_display();
+            ui.label(format!("RGB : {},{},{}",self.r,self.g,self.b));
+            let hsl = HSL::from_rgb(&rgb);
+            ui.label(format!("HSL : {:.2},{:.2},{:.2}",hsl.h,hsl.s,hsl.l));
+            let ok_lab = OkLab::from_rgb(&rgb);
+            let cie_lab = CieLab::from_rgb(rgb);
+            ui.label(format!("OkLab : {:.2},{:.2},{:.2}",ok_lab.l,ok_lab.a,ok_lab.b));
+            ui.label(format!("CieLab : {:.2},{:.2},{:.2}",cie_lab.l,cie_lab.a,cie_lab.b));
+            ui.with_layout(egui::Layout::top_down(egui::Align::LEFT),|ui|{
+                if let Some(rect) = &view.img_rect {
+                    ui.add(
+                        egui::Image::from_texture(rect)
+                    );
                 }
-                ui.label(format!("Image position : {} / {}",self.position[0],self.position[1]));
-                let rgb = Rgb::from([self.r,self.g,self.b]);
-                ui.label(format!("RGB : {},{},{}",self.r,self.g,self.b));
-                let hsl = HSL::from_rgb(&rgb);
-                ui.label(format!("HSL : {:.2},{:.2},{:.2}",hsl.h,hsl.s,hsl.l));
-                let ok_lab = OkLab::from_rgb(&rgb);
-                let cie_lab = CieLab::from_rgb(rgb);
-                ui.label(format!("OkLab : {:.2},{:.2},{:.2}",ok_lab.l,ok_lab.a,ok_lab.b));
-                ui.label(format!("CieLab : {:.2},{:.2},{:.2}",cie_lab.l,cie_lab.a,cie_lab.b));
-                ui.with_layout(egui::Layout::top_down(egui::Align::LEFT),|ui|{
-                    if let Some(rect) = &self.img_rect {
-                        ui.add(
-                            egui::Image::from_texture(rect)
-                        );
-                    }else {
-                        ui.label("No Color Display Texture found");
-                    };
-                    if let Some(bar) = &self.img_bar {
-                        ui.add(
-                            egui::Image::from_texture(bar)
-                        );
-                    }else {
-                        ui.label("No Color Display Texture found");
-                        if ui.button("Generate").clicked() {
-                            self.generate_color_display();
-                        }
-                    };
-                });
-                if ui.checkbox(&mut self.mark_every_color,"Select every color").clicked(){
-                    for c in &mut self.colors.iter_mut(){
-                       c.marked = self.mark_every_color; 
-                    }
-                };
-                egui::CollapsingHeader::new("Colors").show(ui,|ui|{
-                    if self.colors.len() > 0 {
-                        egui::ScrollArea::vertical().max_height(100.0).auto_shrink([false,true]).show(ui, |ui| {
-                            let aw = ui.available_width();
-                            egui::Grid::new("Colors").spacing(Vec2::new(0.0,3.0)).show(ui,|ui|{
-                                let mut column_count = 0;
-                                for c in &mut self.colors{
-                                    color_display(ui, c);
-                                    column_count += 1;
-                                    if column_count > (aw/(ui.available_width()+3.0)) as i32 {
-                                        ui.end_row();
-                                        column_count = 0;
-                                    }
+                if let Some(bar) = &view.img_bar {
+                    ui.add(
+                        egui::Image::from_texture(bar)
+                    );
+                }
+            });
+            if ui.checkbox(&mut view.mark_every_color,"Select every color").clicked(){
+                for c in &mut self.colors.iter_mut(){
+                   c.marked = view.mark_every_color; 
+                }
+            };
+            egui::CollapsingHeader::new("Colors").show(ui,|ui|{
+                if !self.colors.is_empty() {
+                    egui::ScrollArea::vertical().max_height(100.0).auto_shrink([false,true]).show(ui, |ui| {
+                        let aw = ui.available_width();
+                        egui::Grid::new("Colors").spacing(Vec2::new(0.0,3.0)).show(ui,|ui|{
+                            let mut column_count = 0;
+                            for (i,c) in self.colors.iter_mut().enumerate(){
+                                color_display(ui, c,id.with(i));
+                                column_count += 1;
+                                if column_count > (aw/(ui.available_width()+3.0)) as i32 {
+                                    ui.end_row();
+                                    column_count = 0;
                                 }
-                            });
+                            }
                         });
-                    }
-                });
+                    });
+                }
             });
-            self.color_info_window_open = window_open;
-            for w in &mut self.colors{
-                w.color_info_window_show(ctx);
-            }
+        });
+        view.info_window_open = window_open;
+        view.store(ctx,id);
+        for (i,w) in self.colors.iter_mut().enumerate(){
+            w.color_info_window_show(ctx,id.with(i));
         }
     }
 }
 
-impl fmt::Display for AvarageRgb {
+impl fmt::Display for PaletteColor {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f,"({},{},{})",self.r,self.g,self.b)
     }
 }
 
-impl PartialEq for AvarageRgb {
+impl PartialEq for PaletteColor {
     fn eq(&self, other: &Self) -> bool {
         rgb_distance(self.to_rgb(),other.to_rgb()) <= 1.0
     }
@@ -370,9 +364,9 @@ impl XYZ {
 pub const XYZ_D65:XYZ = XYZ{x:95.047,y:100.0,z:108.883};
 
 pub struct CieLab {
-    l:f32,
-    a:f32,
-    b:f32,
+    pub l:f32,
+    pub a:f32,
+    pub b:f32,
 }
 
 impl CieLab {
@@ -387,6 +381,52 @@ impl CieLab {
     pub fn distance_to_lab(&self,comp:&CieLab) -> f32 {
         ((self.l - comp.l).powf(2.0)+(self.a - comp.a).powf(2.0)+(self.b - comp.b).powf(2.0)).sqrt()
     }
+    // CIEDE2000 with the reference conditions kl = kc = kh = 1
+    pub fn delta_e_2000(&self,comp:&CieLab) -> f32 {
+        let (l1,a1,b1) = (self.l as f64,self.a as f64,self.b as f64);
+        let (l2,a2,b2) = (comp.l as f64,comp.a as f64,comp.b as f64);
+        let c_mean = ((a1 * a1 + b1 * b1).sqrt() + (a2 * a2 + b2 * b2).sqrt()) / 2.0;
+        let g = 0.5 * (1.0 - (c_mean.powi(7) / (c_mean.powi(7) + 25.0_f64.powi(7))).sqrt());
+        let (a1,a2) = (a1 * (1.0 + g),a2 * (1.0 + g));
+        let (c1,c2) = ((a1 * a1 + b1 * b1).sqrt(),(a2 * a2 + b2 * b2).sqrt());
+        let hue = |b:f64,a:f64| if a == 0.0 && b == 0.0 {0.0} else {b.atan2(a).to_degrees().rem_euclid(360.0)};
+        let (h1,h2) = (hue(b1,a1),hue(b2,a2));
+
+        let delta_l = l2 - l1;
+        let delta_c = c2 - c1;
+        let delta_h = if c1 * c2 == 0.0 {
+            0.0
+        }else if (h2 - h1).abs() <= 180.0 {
+            h2 - h1
+        }else if h2 <= h1 {
+            h2 - h1 + 360.0
+        }else{
+            h2 - h1 - 360.0
+        };
+        let delta_h = 2.0 * (c1 * c2).sqrt() * (delta_h / 2.0).to_radians().sin();
+
+        let l_mean = (l1 + l2) / 2.0;
+        let c_mean = (c1 + c2) / 2.0;
+        let h_mean = if c1 * c2 == 0.0 {
+            h1 + h2
+        }else if (h1 - h2).abs() <= 180.0 {
+            (h1 + h2) / 2.0
+        }else if h1 + h2 < 360.0 {
+            (h1 + h2 + 360.0) / 2.0
+        }else{
+            (h1 + h2 - 360.0) / 2.0
+        };
+        let t = 1.0 - 0.17 * (h_mean - 30.0).to_radians().cos() + 0.24 * (2.0 * h_mean).to_radians().cos()
+            + 0.32 * (3.0 * h_mean + 6.0).to_radians().cos() - 0.20 * (4.0 * h_mean - 63.0).to_radians().cos();
+        let delta_theta = 30.0 * (-((h_mean - 275.0) / 25.0).powi(2)).exp();
+        let r_c = 2.0 * (c_mean.powi(7) / (c_mean.powi(7) + 25.0_f64.powi(7))).sqrt();
+        let s_l = 1.0 + 0.015 * (l_mean - 50.0).powi(2) / (20.0 + (l_mean - 50.0).powi(2)).sqrt();
+        let s_c = 1.0 + 0.045 * c_mean;
+        let s_h = 1.0 + 0.015 * c_mean * t;
+        let r_t = -(2.0 * delta_theta).to_radians().sin() * r_c;
+
+        ((delta_l / s_l).powi(2) + (delta_c / s_c).powi(2) + (delta_h / s_h).powi(2) + r_t * (delta_c / s_c) * (delta_h / s_h)).sqrt() as f32
+    }
 
     pub fn from_xyz(xyz:&XYZ) -> Self{
         let mut var_x = xyz.x/XYZ_D65.x;
@@ -406,6 +446,24 @@ impl CieLab {
     pub fn from_rgb(rgb:Rgb<u8>)-> Self{
         Self::from_xyz(&XYZ::from_rgb(&rgb))
     }
+    pub fn to_rgb(&self) -> Rgb<u8> {
+        let var_y = (self.l + 16.0) / 116.0;
+        let var_x = self.a / 500.0 + var_y;
+        let var_z = var_y - self.b / 200.0;
+
+        let inv = |v:f32| if v.powi(3) > 0.008856 {v.powi(3)} else {(v - 16.0/116.0) / 7.787};
+
+        let x = inv(var_x) * XYZ_D65.x / 100.0;
+        let y = inv(var_y) * XYZ_D65.y / 100.0;
+        let z = inv(var_z) * XYZ_D65.z / 100.0;
+
+        let r = x *  3.2406 + y * -1.5372 + z * -0.4986;
+        let g = x * -0.9689 + y *  1.8758 + z *  0.0415;
+        let b = x *  0.0557 + y * -0.2040 + z *  1.0570;
+
+        let to_u8 = |c:f32| (gamma_compress(c as f64).clamp(0.0,1.0) * 255.0).round() as u8;
+        Rgb::from([to_u8(r),to_u8(g),to_u8(b)])
+    }
 }
 
 impl fmt::Display for CieLab {
@@ -426,14 +484,14 @@ impl PartialEq for OkLab {
         delta <= OKLAB_TOLERANCE
   }  
 }
-fn gamma_expand(c: f64) -> f64 {
+pub fn gamma_expand(c: f64) -> f64 {
     if c <= 0.04045 {
         c / 12.92
     } else {
         ((c + 0.055) / 1.055).powf(2.4)
     }
 }
-fn gamma_compress(c: f64) -> f64 {
+pub fn gamma_compress(c: f64) -> f64 {
     if c <= 0.0031308 {
         12.92 * c
     } else {
@@ -689,3 +747,33 @@ impl fmt::Display for HSL {
         write!(f,"({},{},{})",self.h,self.s,self.l)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delta_e_2000_reference_pairs() {
+        // from the test data of Sharma, Wu and Dalal, "The CIEDE2000 color-difference formula" (2005)
+        let pairs = [
+            ([50.0,2.6772,-79.7751],[50.0,0.0,-82.7485],2.0425),
+            ([50.0,3.1571,-77.2803],[50.0,0.0,-82.7485],2.8615),
+            ([50.0,0.0,0.0],[50.0,-1.0,2.0],2.3669),
+            ([50.0,-1.0,2.0],[50.0,0.0,0.0],2.3669),
+            ([50.0,2.49,-0.001],[50.0,-2.49,0.0009],7.1792),
+            ([50.0,2.49,-0.001],[50.0,-2.49,0.0011],7.2195),
+            ([50.0,2.5,0.0],[73.0,25.0,-18.0],27.1492),
+            ([50.0,2.5,0.0],[50.0,3.1736,0.5854],1.0),
+            ([60.2574,-34.0099,36.2677],[60.4626,-34.1751,39.4387],1.2644),
+            ([90.8027,-2.0831,1.441],[91.1528,-1.6435,0.0447],1.4441),
+            ([2.0776,0.0795,-1.135],[0.9033,-0.0636,-0.5514],0.9082),
+        ];
+        for (a,b,expected) in pairs {
+            let (a,b) = (CieLab::new(a[0],a[1],a[2]),CieLab::new(b[0],b[1],b[2]));
+            let delta = a.delta_e_2000(&b);
+            assert!((delta - expected).abs() < 1e-3,"{:?} {:?}: {} instead of {}",(a.l,a.a,a.b),(b.l,b.a,b.b),delta,expected);
+        }
+        let grey = CieLab::new(40.0,0.0,0.0);
+        assert_eq!(grey.delta_e_2000(&grey),0.0);
+    }
+}
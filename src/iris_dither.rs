@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+use std::fmt;
+use image::{DynamicImage, GenericImageView, Rgb, Rgba, RgbaImage};
+
+use super::iris_color::{self, ColorSpace};
+
+// (dx,dy,weight) of the error handed to the neighbours
+type Kernel = &'static [(i32,i32,f32)];
+
+#[derive(Debug,PartialEq,Clone,Copy,Default)]
+pub enum DitherMode {
+    #[default]
+    None,
+    FloydSteinberg,
+    Atkinson,
+    JarvisJudiceNinke,
+    Bayer2,
+    Bayer4,
+    Bayer8,
+}
+
+impl DitherMode {
+    pub const ALL:[DitherMode;7] = [Self::None,Self::FloydSteinberg,Self::Atkinson,Self::JarvisJudiceNinke,Self::Bayer2,Self::Bayer4,Self::Bayer8];
+
+    // kernel and the divisor of its weights
+    fn diffusion(self) -> Option<(Kernel,f32)> {
+        match self {
+            Self::FloydSteinberg => Some((&[(1,0,7.0),(-1,1,3.0),(0,1,5.0),(1,1,1.0)],16.0)),
+            // only 6/8 of the error is spread, so contrast is kept
+            Self::Atkinson => Some((&[(1,0,1.0),(2,0,1.0),(-1,1,1.0),(0,1,1.0),(1,1,1.0),(0,2,1.0)],8.0)),
+            Self::JarvisJudiceNinke => Some((&[
+                (1,0,7.0),(2,0,5.0),
+                (-2,1,3.0),(-1,1,5.0),(0,1,7.0),(1,1,5.0),(2,1,3.0),
+                (-2,2,1.0),(-1,2,3.0),(0,2,5.0),(1,2,3.0),(2,2,1.0),
+            ],48.0)),
+            _ => None,
+        }
+    }
+
+    fn bayer_size(self) -> Option<u32> {
+        match self {
+            Self::Bayer2 => Some(2),
+            Self::Bayer4 => Some(4),
+            Self::Bayer8 => Some(8),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for DitherMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::None => write!(f,"None"),
+            Self::FloydSteinberg => write!(f,"Floyd-Steinberg"),
+            Self::Atkinson => write!(f,"Atkinson"),
+            Self::JarvisJudiceNinke => write!(f,"Jarvis-Judice-Ninke"),
+            Self::Bayer2 => write!(f,"Bayer 2x2"),
+            Self::Bayer4 => write!(f,"Bayer 4x4"),
+            Self::Bayer8 => write!(f,"Bayer 8x8"),
+        }
+    }
+}
+
+// threshold of the recursive bayer matrix at x,y, between 0 and 1
+fn bayer_threshold(size:u32,x:u32,y:u32) -> f32 {
+    let mut value = 0;
+    let mut bit = size / 2;
+    let (mut x,mut y) = (x % size,y % size);
+    let mut level = 1;
+    while bit > 0 {
+        let quadrant = match (x >= bit,y >= bit) {
+            (false,false) => 0,
+            (true,true) => 1,
+            (true,false) => 2,
+            (false,true) => 3,
+        };
+        // the outer quadrant is the least significant digit
+        value += quadrant * level;
+        x %= bit;
+        y %= bit;
+        bit /= 2;
+        level *= 4;
+    }
+    (value as f32 + 0.5) / (size * size) as f32
+}
+
+fn coordinates(rgb:Rgb<u8>,space:ColorSpace) -> [f32;3] {
+    match space {
+        ColorSpace::Rgb => rgb.0.map(|c| c as f32),
+        ColorSpace::CieLab => {
+            let lab = iris_color::CieLab::from_rgb(rgb);
+            [lab.l,lab.a,lab.b]
+        }
+        ColorSpace::OkLab => {
+            let lab = iris_color::OkLab::from_rgb(&rgb);
+            [lab.l,lab.a,lab.b]
+        }
+    }
+}
+
+struct NearestColor {
+    palette:Vec<(Rgb<u8>,[f32;3])>,
+    space:ColorSpace,
+    cache:HashMap<[u8;3],Rgb<u8>>,
+}
+
+impl NearestColor {
+    fn find(&mut self,rgb:Rgb<u8>) -> Rgb<u8> {
+        let palette = &self.palette;
+        let space = self.space;
+        *self.cache.entry(rgb.0).or_insert_with(|| {
+            let target = coordinates(rgb,space);
+            palette.iter().min_by(|a,b| distance(a.1,target).total_cmp(&distance(b.1,target))).map(|c| c.0).unwrap_or(rgb)
+        })
+    }
+}
+
+fn distance(a:[f32;3],b:[f32;3]) -> f32 {
+    (a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)
+}
+
+fn to_rgb(c:[f32;3]) -> Rgb<u8> {
+    Rgb(c.map(|c| c.round().clamp(0.0,255.0) as u8))
+}
+
+// redraws the image with nothing but the palette colors, transparent pixels are left as they are
+pub fn palette_map(image:&DynamicImage,palette:&[Rgb<u8>],space:ColorSpace,dither:DitherMode) -> RgbaImage {
+    let (width,height) = image.dimensions();
+    let mut out = image.to_rgba8();
+    if palette.is_empty() {
+        return out;
+    }
+    let mut nearest = NearestColor{palette:palette.iter().map(|c| (*c,coordinates(*c,space))).collect(),space,cache:HashMap::new()};
+
+    if let Some(size) = dither.bayer_size() {
+        // the spread shrinks with bigger palettes, their colors sit closer together
+        let spread = 255.0 / (palette.len() as f32).cbrt().max(1.0);
+        for (x,y,pixel) in out.enumerate_pixels_mut() {
+            if pixel.0[3] == 0 {
+                continue;
+            }
+            let offset = (bayer_threshold(size,x,y) - 0.5) * spread;
+            let rgb = to_rgb([pixel.0[0],pixel.0[1],pixel.0[2]].map(|c| c as f32 + offset));
+            let [r,g,b] = nearest.find(rgb).0;
+            *pixel = Rgba([r,g,b,pixel.0[3]]);
+        }
+        return out;
+    }
+
+    let Some((kernel,divisor)) = dither.diffusion() else {
+        for pixel in out.pixels_mut() {
+            if pixel.0[3] == 0 {
+                continue;
+            }
+            let [r,g,b] = nearest.find(Rgb([pixel.0[0],pixel.0[1],pixel.0[2]])).0;
+            *pixel = Rgba([r,g,b,pixel.0[3]]);
+        }
+        return out;
+    };
+    // error diffusion runs on the 8 bit values, the error is kept in floats
+    let mut values:Vec<[f32;3]> = out.pixels().map(|p| [p.0[0] as f32,p.0[1] as f32,p.0[2] as f32]).collect();
+    for y in 0..height {
+        for x in 0..width {
+            let index = (y * width + x) as usize;
+            let alpha = out.get_pixel(x,y).0[3];
+            if alpha == 0 {
+                continue;
+            }
+            let old = values[index];
+            let new = nearest.find(to_rgb(old));
+            out.put_pixel(x,y,Rgba([new[0],new[1],new[2],alpha]));
+            let error = [old[0] - new[0] as f32,old[1] - new[1] as f32,old[2] - new[2] as f32];
+            for (dx,dy,weight) in kernel {
+                let (nx,ny) = (x as i32 + dx,y as i32 + dy);
+                if nx < 0 || nx >= width as i32 || ny >= height as i32 {
+                    continue;
+                }
+                let neighbour = &mut values[(ny as u32 * width + nx as u32) as usize];
+                for c in 0..3 {
+                    neighbour[c] += error[c] * weight / divisor;
+                }
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PALETTE:[Rgb<u8>;4] = [Rgb([0,0,0]),Rgb([255,255,255]),Rgb([200,30,30]),Rgb([30,60,200])];
+
+    // gradient with a transparent last column
+    fn gradient() -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(17,9,|x,y| {
+            if x == 16 {Rgba([90,10,10,0])} else {Rgba([(x * 15) as u8,(y * 28) as u8,((x + y) * 9) as u8,255])}
+        }))
+    }
+
+    #[test]
+    fn every_mode_uses_only_palette_colors() {
+        let image = gradient();
+        for space in [ColorSpace::Rgb,ColorSpace::CieLab,ColorSpace::OkLab] {
+            for mode in DitherMode::ALL {
+                let mapped = palette_map(&image,&PALETTE,space,mode);
+                for (x,_,pixel) in mapped.enumerate_pixels() {
+                    if x == 16 {
+                        assert_eq!(pixel.0,[90,10,10,0],"{} {:?}",mode,space);
+                    }else{
+                        assert!(PALETTE.contains(&Rgb([pixel.0[0],pixel.0[1],pixel.0[2]])) && pixel.0[3] == 255,"{} {:?} {:?}",mode,space,pixel);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn flat_image_maps_to_the_nearest_color() {
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(4,4,Rgba([190,40,45,255])));
+        for space in [ColorSpace::Rgb,ColorSpace::CieLab,ColorSpace::OkLab] {
+            let mapped = palette_map(&image,&PALETTE,space,DitherMode::None);
+            assert!(mapped.pixels().all(|p| p.0 == [200,30,30,255]),"{:?}",space);
+        }
+        // nothing to map to, the image comes back unchanged
+        assert_eq!(palette_map(&image,&[],ColorSpace::OkLab,DitherMode::FloydSteinberg),image.to_rgba8());
+    }
+
+    #[test]
+    fn bayer_thresholds_are_evenly_spread() {
+        for size in [2,4,8] {
+            let mut thresholds:Vec<f32> = (0..size).flat_map(|y| (0..size).map(move |x| bayer_threshold(size,x,y))).collect();
+            thresholds.sort_by(|a,b| a.total_cmp(b));
+            let expected:Vec<f32> = (0..size * size).map(|i| (i as f32 + 0.5) / (size * size) as f32).collect();
+            assert_eq!(thresholds,expected);
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use std::path::{Path, PathBuf};
+
+use super::{iris_image_io, iris_palette, iris_project};
+
+pub const RECENT_FILES_KEY:&str = "iris_recent_files";
+const RECENT_FILES_LIMIT:usize = 10;
+
+pub fn is_image_file(path:&Path) -> bool {
+    image::ImageFormat::from_path(path).is_ok_and(iris_image_io::can_decode)
+}
+
+pub fn is_supported_file(path:&Path) -> bool {
+    is_image_file(path) || iris_palette::is_palette_file(path) || iris_project::is_project_file(path)
+}
+
+pub fn add_recent_file(recent_files:&mut Vec<PathBuf>,path:PathBuf){
+    let path = std::path::absolute(&path).unwrap_or(path);
+    recent_files.retain(|p| *p != path);
+    recent_files.insert(0,path);
+    recent_files.truncate(RECENT_FILES_LIMIT);
+}
+
+// egui only file browser, so opening files works without a native dialog backend
+pub struct FileBrowser {
+    pub open:bool,
+    dir:PathBuf,
+    dir_text:String,
+    entries:Vec<(PathBuf,bool)>,
+    selected:Option<PathBuf>,
+    show_all:bool,
+    error:Option<String>,
+}
+
+impl Default for FileBrowser {
+    fn default() -> Self {
+        let dir = std::env::current_dir().unwrap_or_default();
+        Self{
+            open:false,
+            dir_text:dir.to_string_lossy().to_string(),
+            dir,
+            entries:vec![],
+            selected:None,
+            show_all:false,
+            error:None,
+        }
+    }
+}
+
+impl FileBrowser {
+    pub fn open(&mut self){
+        self.open = true;
+        self.selected = None;
+        self.read_dir();
+    }
+
+    fn change_dir(&mut self,dir:PathBuf){
+        self.dir = dir;
+        self.dir_text = self.dir.to_string_lossy().to_string();
+        self.selected = None;
+        self.read_dir();
+    }
+
+    fn read_dir(&mut self){
+        self.entries.clear();
+        self.error = None;
+        match std::fs::read_dir(&self.dir) {
+            Ok(read_dir) => {
+                for entry in read_dir.flatten() {
+                    let path = entry.path();
+                    let is_dir = path.is_dir();
+                    let hidden = entry.file_name().to_string_lossy().starts_with('.');
+                    if hidden && !self.show_all {
+                        continue;
+                    }
+                    if is_dir || self.show_all || is_supported_file(&path) {
+                        self.entries.push((path,is_dir));
+                    }
+                }
+                // folders first, then by name
+                self.entries.sort_by(|a,b| b.1.cmp(&a.1).then_with(|| a.0.file_name().cmp(&b.0.file_name())));
+            },
+            Err(err) => self.error = Some(format!("{}: {}",self.dir.display(),err)),
+        }
+    }
+
+    // returns the file the user picked this frame
+    pub fn show(&mut self,ctx:&egui::Context) -> Option<PathBuf> {
+        if !self.open {
+            return None;
+        }
+        let mut window_open = self.open;
+        let mut picked = None;
+        let mut change_dir = None;
+        egui::Window::new("Open File").id(egui::Id::new("iris_file_browser")).open(&mut window_open).default_size([420.0,360.0]).show(ctx,|ui| {
+            ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP),|ui| {
+                if ui.add_enabled(self.dir.parent().is_some(),egui::Button::new("Up")).clicked(){
+                    change_dir = self.dir.parent().map(|p| p.to_path_buf());
+                }
+                let response = ui.text_edit_singleline(&mut self.dir_text);
+                if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    let path = PathBuf::from(self.dir_text.trim());
+                    if path.is_dir() {
+                        change_dir = Some(path);
+                    }else{
+                        picked = Some(path);
+                    }
+                }
+            });
+            if ui.checkbox(&mut self.show_all,"Show all files").changed(){
+                self.read_dir();
+            }
+            ui.separator();
+            if let Some(err) = &self.error {
+                ui.colored_label(ui.visuals().error_fg_color,err);
+            }
+            egui::ScrollArea::vertical().max_height(260.0).auto_shrink([false,true]).show(ui,|ui| {
+                for (path,is_dir) in self.entries.iter() {
+                    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+                    let label = if *is_dir {format!("[{}]",name)} else {name};
+                    let response = ui.selectable_label(self.selected.as_ref() == Some(path),label);
+                    if response.clicked() {
+                        self.selected = Some(path.clone());
+                    }
+                    if response.double_clicked() {
+                        if *is_dir {
+                            change_dir = Some(path.clone());
+                        }else{
+                            picked = Some(path.clone());
+                        }
+                    }
+                }
+            });
+            ui.separator();
+            ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP),|ui| {
+                let selected_file = self.selected.as_ref().filter(|p| !p.is_dir());
+                if ui.add_enabled(selected_file.is_some(),egui::Button::new("Open")).clicked(){
+                    picked = selected_file.cloned();
+                }
+                if ui.button("Cancel").clicked(){
+                    self.open = false;
+                }
+            });
+        });
+        if let Some(dir) = change_dir {
+            self.change_dir(dir);
+        }
+        self.open &= window_open;
+        if picked.is_some() {
+            self.open = false;
+        }
+        picked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(browser:&FileBrowser) -> Vec<String> {
+        browser.entries.iter().map(|(p,_)| p.file_name().unwrap().to_string_lossy().to_string()).collect()
+    }
+
+    #[test]
+    fn lists_folders_then_supported_files() {
+        assert!(is_image_file(Path::new("a.PNG")) && is_image_file(Path::new("b.jpeg")));
+        assert!(!is_image_file(Path::new("notes.txt")) && !is_image_file(Path::new("no_extension")));
+        assert!(is_image_file(Path::new("scan.exr")) && is_image_file(Path::new("icon.ico")) );
+        assert_eq!(is_image_file(Path::new("photo.avif")),cfg!(feature = "avif"));
+        let dir = std::env::temp_dir().join(format!("iris_browser_{}",std::process::id()));
+        for sub in ["b_dir","A_dir",".hidden_dir"] {
+            std::fs::create_dir_all(dir.join(sub)).unwrap();
+        }
+        for file in ["z.png","c.jpg","palette.gpl","session.iris","notes.md",".hidden.png"] {
+            std::fs::write(dir.join(file),[]).unwrap();
+        }
+        let mut browser = FileBrowser::default();
+        browser.change_dir(dir.clone());
+        assert!(browser.error.is_none());
+        assert_eq!(names(&browser),["A_dir","b_dir","c.jpg","palette.gpl","session.iris","z.png"]);
+        assert!(browser.entries[..2].iter().all(|(_,is_dir)| *is_dir));
+        browser.show_all = true;
+        browser.read_dir();
+        assert_eq!(names(&browser),[".hidden_dir","A_dir","b_dir",".hidden.png","c.jpg","notes.md","palette.gpl","session.iris","z.png"]);
+        browser.change_dir(dir.join("missing"));
+        assert!(browser.entries.is_empty() && browser.error.is_some());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn recent_files_are_unique_and_limited() {
+        let mut recent = vec![];
+        for n in 0..RECENT_FILES_LIMIT + 3 {
+            add_recent_file(&mut recent,PathBuf::from(format!("/images/{}.png",n)));
+        }
+        assert_eq!(recent.len(),RECENT_FILES_LIMIT);
+        assert_eq!(recent[0],PathBuf::from(format!("/images/{}.png",RECENT_FILES_LIMIT + 2)));
+        // opening a file again moves it to the front instead of adding it twice
+        add_recent_file(&mut recent,PathBuf::from("/images/5.png"));
+        assert_eq!(recent.len(),RECENT_FILES_LIMIT);
+        assert_eq!(recent[0],PathBuf::from("/images/5.png"));
+        assert_eq!(recent.iter().filter(|p| **p == Path::new("/images/5.png")).count(),1);
+        // relative paths are stored absolute
+        add_recent_file(&mut recent,PathBuf::from("relative.png"));
+        assert!(recent[0].is_absolute());
+        assert_eq!(recent.len(),RECENT_FILES_LIMIT);
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use std::fmt;
+use image::{DynamicImage, GenericImageView, Pixel, Rgb};
+use serde::{Deserialize, Serialize};
+
+use super::iris_color;
+
+// below these greys have no meaningful hue, they are outside every hue range
+const MIN_HUE_CHROMA:f32 = 0.02;
+const MIN_HUE_SATURATION:f32 = 0.05;
+
+#[derive(Debug,PartialEq,Clone,Copy,Serialize,Deserialize)]
+pub enum FilterChannel {
+    OkLightness,
+    OkChroma,
+    OkHue,
+    HslLightness,
+    HslSaturation,
+    HslHue,
+}
+
+impl FilterChannel {
+    pub const ALL:[FilterChannel;6] = [Self::OkLightness,Self::OkChroma,Self::OkHue,Self::HslLightness,Self::HslSaturation,Self::HslHue];
+
+    // smallest and biggest value of the channel
+    pub fn limits(&self) -> (f32,f32) {
+        match self {
+            Self::OkLightness | Self::HslLightness | Self::HslSaturation => (0.0,1.0),
+            Self::OkChroma => (0.0,0.4),
+            Self::OkHue | Self::HslHue => (0.0,360.0),
+        }
+    }
+
+    pub fn is_hue(&self) -> bool {
+        matches!(self,Self::OkHue | Self::HslHue)
+    }
+}
+
+impl fmt::Display for FilterChannel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OkLightness => write!(f,"OkLCh lightness"),
+            Self::OkChroma => write!(f,"OkLCh chroma"),
+            Self::OkHue => write!(f,"OkLCh hue"),
+            Self::HslLightness => write!(f,"HSL lightness"),
+            Self::HslSaturation => write!(f,"HSL saturation"),
+            Self::HslHue => write!(f,"HSL hue"),
+        }
+    }
+}
+
+#[derive(Debug,PartialEq,Clone,Copy,Serialize,Deserialize)]
+pub enum RangeMode {
+    // pixels outside the range are left out
+    Keep,
+    // pixels inside the range are left out
+    Drop,
+}
+
+impl fmt::Display for RangeMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Keep => write!(f,"Keep only"),
+            Self::Drop => write!(f,"Drop"),
+        }
+    }
+}
+
+#[derive(Debug,PartialEq,Clone,Copy,Serialize,Deserialize)]
+pub struct RangeFilter {
+    pub enabled:bool,
+    pub mode:RangeMode,
+    pub channel:FilterChannel,
+    // hue arcs with min above max wrap around 0
+    pub min:f32,
+    pub max:f32,
+}
+
+impl Default for RangeFilter {
+    fn default() -> Self {
+        // shadows and highlights out
+        Self{enabled:true,mode:RangeMode::Keep,channel:FilterChannel::OkLightness,min:0.2,max:0.95}
+    }
+}
+
+impl RangeFilter {
+    pub fn contains(&self,value:f32) -> bool {
+        if self.channel.is_hue() && self.min > self.max {
+            value >= self.min || value <= self.max
+        }else{
+            value >= self.min && value <= self.max
+        }
+    }
+}
+
+#[derive(Debug,PartialEq,Clone,Copy,Serialize,Deserialize)]
+pub struct ExcludedColor {
+    pub rgb:[u8;3],
+    // OkLab distance
+    pub tolerance:f32,
+}
+
+// applied to the pixels before any scan, every enabled range has to let a pixel through
+#[derive(Debug,PartialEq,Clone,Default,Serialize,Deserialize)]
+pub struct ColorFilter {
+    pub ranges:Vec<RangeFilter>,
+    pub excluded:Vec<ExcludedColor>,
+}
+
+impl ColorFilter {
+    pub fn is_active(&self) -> bool {
+        self.ranges.iter().any(|r| r.enabled) || !self.excluded.is_empty()
+    }
+
+    pub fn keeps(&self,rgb:Rgb<u8>) -> bool {
+        let lab = iris_color::OkLab::from_rgb(&rgb);
+        let hsl = iris_color::HSL::from_rgb(&rgb);
+        let chroma = lab.a.hypot(lab.b);
+        for range in self.ranges.iter().filter(|r| r.enabled) {
+            let value = match range.channel {
+                FilterChannel::OkLightness => Some(lab.l),
+                FilterChannel::OkChroma => Some(chroma),
+                FilterChannel::OkHue => (chroma >= MIN_HUE_CHROMA).then(|| lab.b.atan2(lab.a).to_degrees().rem_euclid(360.0)),
+                FilterChannel::HslLightness => Some(hsl.l),
+                FilterChannel::HslSaturation => Some(hsl.s),
+                FilterChannel::HslHue => (hsl.s >= MIN_HUE_SATURATION).then_some(hsl.h),
+            };
+            if value.is_some_and(|v| range.contains(v)) != (range.mode == RangeMode::Keep) {
+                return false;
+            }
+        }
+        !self.excluded.iter().any(|e| iris_color::OkLab::from_rgb(&Rgb(e.rgb)).distance_to_lab(&lab) <= e.tolerance)
+    }
+
+    // one entry per pixel, row by row, set for pixels the filter lets through. transparent pixels are let through,
+    // they are counted as transparent anyway
+    pub fn mask(&self,image:&DynamicImage) -> Vec<bool> {
+        let mut cache:HashMap<[u8;3],bool> = HashMap::new();
+        image.pixels().map(|(_,_,rgba)| {
+            rgba[3] == 0 || *cache.entry(rgba.to_rgb().0).or_insert_with(|| self.keeps(rgba.to_rgb()))
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranges_and_excluded_colors() {
+        let mut filter = ColorFilter::default();
+        assert!(!filter.is_active());
+        filter.ranges.push(RangeFilter::default());
+        assert!(!filter.keeps(Rgb([5,5,5])));
+        assert!(!filter.keeps(Rgb([255,255,255])));
+        assert!(filter.keeps(Rgb([200,40,40])));
+        // reds only, the arc wraps around 0
+        filter.ranges.push(RangeFilter{enabled:true,mode:RangeMode::Keep,channel:FilterChannel::HslHue,min:330.0,max:30.0});
+        assert!(filter.keeps(Rgb([200,40,40])));
+        assert!(!filter.keeps(Rgb([40,200,40])));
+        // greys have no hue, so they aren't red either
+        assert!(!filter.keeps(Rgb([128,128,128])));
+        filter.excluded.push(ExcludedColor{rgb:[200,40,40],tolerance:0.02});
+        assert!(!filter.keeps(Rgb([201,40,41])));
+        assert!(filter.keeps(Rgb([200,40,120])));
+    }
+
+    #[test]
+    fn greys_are_outside_every_hue_range() {
+        for channel in [FilterChannel::OkHue,FilterChannel::HslHue] {
+            // the arc around 0 is where a grey's hue would land
+            let mut range = RangeFilter{enabled:true,mode:RangeMode::Keep,channel,min:330.0,max:30.0};
+            let mut filter = ColorFilter{ranges:vec![range],excluded:vec![]};
+            assert!(!filter.keeps(Rgb([128,128,128])),"{}",channel);
+            assert!(!filter.keeps(Rgb([129,128,128])),"{}",channel);
+            assert!(filter.keeps(Rgb([220,60,70])),"{}",channel);
+            range.mode = RangeMode::Drop;
+            filter.ranges = vec![range];
+            assert!(filter.keeps(Rgb([128,128,128])),"{}",channel);
+            assert!(!filter.keeps(Rgb([220,60,70])),"{}",channel);
+        }
+    }
+}
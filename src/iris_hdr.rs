@@ -0,0 +1,140 @@
+use std::fmt;
+use image::{DynamicImage, Rgba, Rgba32FImage, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use super::iris_color;
+
+#[derive(Debug,PartialEq,Clone,Copy,Default,Serialize,Deserialize)]
+pub enum ToneMapper {
+    Clip,
+    Reinhard,
+    #[default]
+    AcesFilmic,
+    AgX,
+}
+
+impl ToneMapper {
+    pub const ALL:[ToneMapper;4] = [Self::Clip,Self::Reinhard,Self::AcesFilmic,Self::AgX];
+
+    // scene linear in, display linear out
+    pub fn map(self,rgb:[f32;3]) -> [f32;3] {
+        match self {
+            Self::Clip => rgb.map(|c| c.clamp(0.0,1.0)),
+            Self::Reinhard => rgb.map(|c| c.max(0.0) / (1.0 + c.max(0.0))),
+            // Narkowicz fit of the ACES RRT + ODT
+            Self::AcesFilmic => rgb.map(|c| {
+                let x = c.max(0.0) * 0.6;
+                (x * (2.51 * x + 0.03) / (x * (2.43 * x + 0.59) + 0.14)).clamp(0.0,1.0)
+            }),
+            Self::AgX => agx(rgb),
+        }
+    }
+}
+
+impl fmt::Display for ToneMapper {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Clip => write!(f,"Clip"),
+            Self::Reinhard => write!(f,"Reinhard"),
+            Self::AcesFilmic => write!(f,"ACES filmic"),
+            Self::AgX => write!(f,"AgX"),
+        }
+    }
+}
+
+fn mat_mul(m:[[f32;3];3],v:[f32;3]) -> [f32;3] {
+    [
+        m[0][0] * v[0] + m[0][1] * v[1] + m[0][2] * v[2],
+        m[1][0] * v[0] + m[1][1] * v[1] + m[1][2] * v[2],
+        m[2][0] * v[0] + m[2][1] * v[1] + m[2][2] * v[2],
+    ]
+}
+
+// polynomial approximation of the AgX base look (inset, log2 encoding, sigmoid, outset)
+fn agx(rgb:[f32;3]) -> [f32;3] {
+    const INSET:[[f32;3];3] = [
+        [0.8424791,0.0784336,0.07922374],
+        [0.04232824,0.8784686,0.07916613],
+        [0.04237565,0.0784336,0.879143],
+    ];
+    const OUTSET:[[f32;3];3] = [
+        [1.196879,-0.09802088,-0.09902974],
+        [-0.05289685,1.151903,-0.09896118],
+        [-0.05297164,-0.09804345,1.151074],
+    ];
+    const MIN_EV:f32 = -12.47393;
+    const MAX_EV:f32 = 4.026069;
+    let x = mat_mul(INSET,rgb).map(|c| {
+        let x = ((c.max(1e-10).log2() - MIN_EV) / (MAX_EV - MIN_EV)).clamp(0.0,1.0);
+        let x2 = x * x;
+        let x4 = x2 * x2;
+        15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x - 0.00232
+    });
+    mat_mul(OUTSET,x).map(|c| c.max(0.0).powf(2.2).clamp(0.0,1.0))
+}
+
+pub fn is_hdr(image:&DynamicImage) -> bool {
+    matches!(image,DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_))
+}
+
+fn encode(rgb:[f32;3],alpha:f32) -> Rgba<u8> {
+    let [r,g,b] = rgb.map(|c| (iris_color::gamma_compress(c as f64) * 255.0).round().clamp(0.0,255.0) as u8);
+    Rgba([r,g,b,(alpha.clamp(0.0,1.0) * 255.0).round() as u8])
+}
+
+pub fn tone_map(image:&Rgba32FImage,mapper:ToneMapper,exposure:f32) -> RgbaImage {
+    let scale = exposure.exp2();
+    RgbaImage::from_fn(image.width(),image.height(),|x,y| {
+        let [r,g,b,a] = image.get_pixel(x,y).0;
+        encode(mapper.map([r * scale,g * scale,b * scale]),a)
+    })
+}
+
+// share of the pixels that may end up brighter than white, so a few specular highlights or hot pixels
+// don't push everything else into the lowest 8 bit steps
+const SCENE_LINEAR_CLIPPED:f32 = 0.001;
+
+// brightest channel of the pixel at the percentile, zero for black images
+fn white_point(image:&Rgba32FImage) -> f32 {
+    let mut peaks:Vec<f32> = image.pixels().map(|p| [p.0[0],p.0[1],p.0[2]].into_iter().filter(|c| c.is_finite()).fold(0.0_f32,f32::max)).collect();
+    if peaks.is_empty() {
+        return 0.0;
+    }
+    let index = ((peaks.len() - 1) as f32 * (1.0 - SCENE_LINEAR_CLIPPED)).round() as usize;
+    let (_,white,_) = peaks.select_nth_unstable_by(index,|a,b| a.total_cmp(b));
+    *white
+}
+
+// keeps the ratios between the scene values: no curve, only scaled so the white point becomes white, brighter pixels clip.
+// the 8 bit result is still sRGB encoded, so scans decode it back to the same linear ratios
+pub fn scene_linear(image:&Rgba32FImage) -> RgbaImage {
+    let white = white_point(image);
+    let scale = if white > 0.0 {1.0 / white} else {1.0};
+    RgbaImage::from_fn(image.width(),image.height(),|x,y| {
+        let [r,g,b,a] = image.get_pixel(x,y).0;
+        encode([r * scale,g * scale,b * scale].map(|c| if c.is_finite() {c.max(0.0)} else {0.0}),a)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scene_linear_ignores_bright_outliers() {
+        // a dim gradient with one pixel a thousand times brighter
+        let mut image = Rgba32FImage::from_fn(100,100,|x,_| Rgba([0.2 + x as f32 * 0.008,0.1,0.05,1.0]));
+        image.put_pixel(0,0,Rgba([1000.0,1000.0,1000.0,1.0]));
+        image.put_pixel(1,0,Rgba([f32::INFINITY,0.0,0.0,1.0]));
+        let mapped = scene_linear(&image);
+        assert_eq!(mapped.get_pixel(0,0).0,[255,255,255,255]);
+        // the brightest regular pixels reach white instead of being crushed to a few steps
+        assert_eq!(mapped.get_pixel(99,50).0[0],255);
+        let dim = mapped.get_pixel(0,50).0;
+        assert!(dim[0] > 100 && dim[0] > dim[1] && dim[1] > dim[2],"{:?}",dim);
+        // ratios survive the round trip through the srgb encoding
+        let ratio = iris_color::gamma_expand(dim[0] as f64 / 255.0) / iris_color::gamma_expand(dim[1] as f64 / 255.0);
+        assert!((ratio - 2.0).abs() < 0.1,"{}",ratio);
+        assert_eq!(scene_linear(&Rgba32FImage::new(4,4)).get_pixel(0,0).0,[0,0,0,0]);
+    }
+}
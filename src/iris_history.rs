@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+
+use super::iris_color::PaletteColor;
+use super::iris_scan::PixelTotals;
+
+const HISTORY_LIMIT:usize = 100;
+
+#[derive(Clone)]
+pub struct ScanSnapshot {
+    pub color_list:HashMap<u32,PaletteColor>,
+    pub color_percent:HashMap<u32,f32>,
+    pub color_pixel_count:HashMap<u32,u32>,
+    // percentages are shares of the scanned pixels, so the totals go back along with them
+    pub pixel_totals:PixelTotals,
+}
+
+pub enum Command {
+    Scan{window:usize,label:&'static str,before:Box<ScanSnapshot>,after:Box<ScanSnapshot>},
+    ScanMarks{window:usize,before:Vec<bool>,after:Vec<bool>},
+    Palette{label:&'static str,before:Vec<PaletteColor>,after:Vec<PaletteColor>},
+    PaletteMarks{before:Vec<bool>,after:Vec<bool>},
+}
+
+impl Command {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Scan{label,..} | Self::Palette{label,..} => label,
+            Self::ScanMarks{..} | Self::PaletteMarks{..} => "Selection",
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct History {
+    undo_stack:Vec<Command>,
+    redo_stack:Vec<Command>,
+}
+
+impl History {
+    pub fn push(&mut self,command:Command){
+        self.redo_stack.clear();
+        self.undo_stack.push(command);
+        if self.undo_stack.len() > HISTORY_LIMIT {
+            self.undo_stack.remove(0);
+        }
+    }
+    pub fn undo(&mut self) -> Option<&Command> {
+        let command = self.undo_stack.pop()?;
+        self.redo_stack.push(command);
+        self.redo_stack.last()
+    }
+    pub fn redo(&mut self) -> Option<&Command> {
+        let command = self.redo_stack.pop()?;
+        self.undo_stack.push(command);
+        self.undo_stack.last()
+    }
+    pub fn next_undo(&self) -> Option<&'static str> {
+        self.undo_stack.last().map(|c| c.label())
+    }
+    pub fn next_redo(&self) -> Option<&'static str> {
+        self.redo_stack.last().map(|c| c.label())
+    }
+    pub fn clear(&mut self){
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+}
+
+// marks are flattened depth first, so a selection can be stored without cloning the colors
+pub fn collect_marks<'a>(colors:impl Iterator<Item = &'a PaletteColor>) -> Vec<bool> {
+    let mut marks = vec![];
+    for c in colors {
+        marks.push(c.marked);
+        marks.extend(collect_marks(c.colors.iter()));
+    }
+    marks
+}
+
+pub fn apply_marks<'a>(colors:impl Iterator<Item = &'a mut PaletteColor>,marks:&mut impl Iterator<Item = bool>){
+    for c in colors {
+        if let Some(marked) = marks.next() {
+            c.marked = marked;
+        }
+        apply_marks(c.colors.iter_mut(),marks);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgb;
+
+    fn palette(label:&'static str,n:u8) -> Command {
+        Command::Palette{label,before:vec![],after:vec![PaletteColor::from_rgb(Rgb([n,n,n]),[0,0])]}
+    }
+
+    #[test]
+    fn undo_then_redo() {
+        let mut history = History::default();
+        assert!(history.undo().is_none());
+        history.push(palette("Add color",1));
+        history.push(Command::PaletteMarks{before:vec![false],after:vec![true]});
+        assert_eq!(history.next_undo(),Some("Selection"));
+        assert_eq!(history.undo().map(|c| c.label()),Some("Selection"));
+        assert_eq!((history.next_undo(),history.next_redo()),(Some("Add color"),Some("Selection")));
+        assert_eq!(history.undo().map(|c| c.label()),Some("Add color"));
+        assert!(history.undo().is_none());
+        assert_eq!(history.redo().map(|c| c.label()),Some("Add color"));
+        assert_eq!(history.redo().map(|c| c.label()),Some("Selection"));
+        assert!(history.redo().is_none());
+        assert_eq!(history.next_undo(),Some("Selection"));
+    }
+
+    #[test]
+    fn push_clears_redo() {
+        let mut history = History::default();
+        history.push(palette("Add color",1));
+        history.push(palette("Pick color",2));
+        history.undo();
+        assert_eq!(history.next_redo(),Some("Pick color"));
+        history.push(palette("Remove colors",3));
+        assert_eq!(history.next_redo(),None);
+        assert!(history.redo().is_none());
+        assert_eq!(history.next_undo(),Some("Remove colors"));
+        history.clear();
+        assert_eq!((history.next_undo(),history.next_redo()),(None,None));
+    }
+
+    #[test]
+    fn oldest_commands_are_dropped() {
+        let mut history = History::default();
+        for n in 0..HISTORY_LIMIT + 5 {
+            history.push(palette("Add color",n as u8));
+        }
+        let mut undone = vec![];
+        while let Some(Command::Palette{after,..}) = history.undo() {
+            undone.push(after[0].r);
+        }
+        assert_eq!(undone.len(),HISTORY_LIMIT);
+        // the newest come back first, the first five are gone
+        assert_eq!(undone.first(),Some(&(HISTORY_LIMIT as u8 + 4)));
+        assert_eq!(undone.last(),Some(&5));
+    }
+
+    #[test]
+    fn marks_round_trip() {
+        let mut colors = [PaletteColor::from_rgb(Rgb([1,1,1]),[0,0]),PaletteColor::from_rgb(Rgb([2,2,2]),[0,0])];
+        colors[0].colors.push(PaletteColor::from_rgb(Rgb([3,3,3]),[0,0]));
+        colors[0].colors[0].marked = true;
+        colors[1].marked = true;
+        let marks = collect_marks(colors.iter());
+        assert_eq!(marks,vec![false,true,true]);
+        apply_marks(colors.iter_mut(),&mut vec![true,false,false].into_iter());
+        assert!(colors[0].marked && !colors[0].colors[0].marked && !colors[1].marked);
+        apply_marks(colors.iter_mut(),&mut marks.into_iter());
+        assert_eq!(collect_marks(colors.iter()),vec![false,true,true]);
+        // a shorter list leaves the rest as it is
+        apply_marks(colors.iter_mut(),&mut vec![true].into_iter());
+        assert_eq!(collect_marks(colors.iter()),vec![true,true,true]);
+    }
+}
@@ -2,12 +2,21 @@ use std::fmt::Display;
 use std::{f32::consts::PI,path::PathBuf,collections::HashMap};
 use egui::ColorImage;
 use image::{DynamicImage, GenericImageView, Pixel, RgbaImage};
-use image::{ Rgb, RgbImage,ImageReader};
+use image::{ Rgb, RgbImage};
 
-use crate::iris_color::AvarageRgb;
+use crate::iris_color::PaletteColor;
 
 use super::WINDOW_ID;
 use super::iris_color;
+use super::iris_dither;
+use super::iris_metrics;
+
+#[derive(Debug,PartialEq,Clone,Copy,Default)]
+pub enum MaskStyle {
+    #[default]
+    Dim,
+    Outline,
+}
 
 #[derive(Default)]
 pub struct ImageCreator{
@@ -34,10 +43,10 @@ impl ImageCreator {
             egui::Window::new("ImageCreator").id(egui::Id::new(self.id)).open(&mut window_open).show(ctx,|ui|{
                 if ui.add(egui::Button::new("gen")).clicked() {
                     let mut rect = PieColorComp::new(vec![
-                        AvarageRgb::from_rgb(Rgb::from([255,0,0]),[0,0]),
-                        AvarageRgb::from_rgb(Rgb::from([0,255,0]),[0,0]),
-                        // AvarageRgb::from_rgb(Rgb::from([127,255,0])),
-                        AvarageRgb::from_rgb(Rgb::from([0,0,255]),[0,0])
+                        PaletteColor::from_rgb(Rgb::from([255,0,0]),[0,0]),
+                        PaletteColor::from_rgb(Rgb::from([0,255,0]),[0,0]),
+                        // PaletteColor::from_rgb(Rgb::from([127,255,0])),
+                        PaletteColor::from_rgb(Rgb::from([0,0,255]),[0,0])
                     ],64);
                     rect.generate_pie();
                     rect.save_img();
@@ -159,7 +168,7 @@ impl HSLRect {
             m.draw_bar(self);
         }
     }
-    pub fn add_marker(&mut self,new_color:&mut AvarageRgb,size:u32,border_size:u32) -> bool {
+    pub fn add_marker(&mut self,new_color:&mut PaletteColor,size:u32,border_size:u32) -> bool {
         if new_color.marked{
             let new_marker = RGBMarker::new(new_color.to_rgb(),size,border_size);
             self.obj.push(new_marker);
@@ -169,7 +178,7 @@ impl HSLRect {
         }
     }
     #[allow(dead_code)]
-    pub fn remove_marker(&mut self,new_color:&mut AvarageRgb) -> bool {
+    pub fn remove_marker(&mut self,new_color:&mut PaletteColor) -> bool {
         let rgb = new_color.to_rgb();
         let index = self.obj.iter().position(|r| r.rgb == rgb); 
         if let Some(i) = index {
@@ -195,14 +204,14 @@ pub struct RGBMarker {
     rgb:Rgb<u8>,
     size:u32,
     border_size:u32,
-    avarage_rgb:Option<AvarageRgb>,
+    avarage_rgb:Option<PaletteColor>,
 }
 
 impl RGBMarker {
     pub fn new(rgb:Rgb<u8>,size:u32,border_size:u32) -> Self{
         Self{rgb,size,border_size,avarage_rgb:None} 
     } 
-    pub fn from_avarage_rgb(avarage_rgb:AvarageRgb,size:u32,border_size:u32) -> Self{
+    pub fn from_avarage_rgb(avarage_rgb:PaletteColor,size:u32,border_size:u32) -> Self{
         let rgb = Rgb::from([avarage_rgb.r,avarage_rgb.g,avarage_rgb.b]);
         Self{
             rgb,
@@ -213,6 +222,26 @@ impl RGBMarker {
     }
 }
 
+impl RGBMarker {
+    // outline in the marker color, framed black so it shows on any background
+    fn draw_bounding_box(&self,parent: &mut ImageEditor,bounding_box:[u32;4]){
+        let [min_x,min_y,max_x,max_y] = bounding_box;
+        let max_x = max_x.min(parent.img_width.saturating_sub(1));
+        let max_y = max_y.min(parent.img_hight.saturating_sub(1));
+        let width = (self.border_size/2).max(1);
+        for x in min_x..=max_x {
+            for y in min_y..=max_y {
+                let edge = (x - min_x).min(max_x - x).min(y - min_y).min(max_y - y);
+                if edge < width {
+                    parent.img.put_pixel(x, y,self.rgb.to_rgba());
+                }else if edge < width * 2 {
+                    parent.img.put_pixel(x, y,Rgb::from([0,0,0]).to_rgba());
+                }
+            }
+        }
+    }
+}
+
 impl Draw for RGBMarker{
     fn draw_rect(&mut self,parent: &mut HSLRect) {
         let rgb_pos = parent.rgb_color_to_position_rect(&self.rgb);
@@ -268,7 +297,10 @@ impl Draw for RGBMarker{
     }
     fn draw_avarage_pos(&self,parent: &mut ImageEditor){
         if let Some(av_rgb) = &self.avarage_rgb {
-            let rgb_pos = av_rgb.position;
+            if let Some(spatial) = &av_rgb.spatial {
+                self.draw_bounding_box(parent,spatial.bounding_box);
+            }
+            let rgb_pos = av_rgb.spatial.map(|s| s.centroid.map(|c| c.round() as u32)).unwrap_or(av_rgb.position);
             let x_start = if let Some(val) = rgb_pos[0].checked_sub(self.size) {val} else {0};
             let y_start = if let Some(val) = rgb_pos[1].checked_sub(self.size) {val} else {0};
             let x_end = if let Some(val) = rgb_pos[0].checked_add(self.size) {val} else {u32::MAX};
@@ -296,11 +328,11 @@ impl Draw for RGBMarker{
 pub struct PieColorComp {
     pub img:RgbImage,
     pub size:u32,
-    pub colors:Vec<AvarageRgb>
+    pub colors:Vec<PaletteColor>
 }
 
 impl PieColorComp {
-    pub fn new(colors:Vec<AvarageRgb>,size:u32) -> Self {
+    pub fn new(colors:Vec<PaletteColor>,size:u32) -> Self {
         let img = RgbImage::new(size,size);
         Self {
             img,
@@ -340,10 +372,57 @@ impl PieColorComp {
     }
     
 }
+
+// one column per frame, the dominant colors of a frame are stacked by their share
+pub struct BarcodeStrip {
+    pub img:RgbImage,
+    pub column_width:u32,
+    pub height:u32,
+    pub frames:Vec<Vec<(Rgb<u8>,f32)>>,
+}
+
+impl BarcodeStrip {
+    pub fn new(frames:Vec<Vec<(Rgb<u8>,f32)>>,column_width:u32,height:u32) -> Self {
+        let img = RgbImage::new((frames.len() as u32 * column_width).max(1),height);
+        Self {
+            img,
+            column_width,
+            height,
+            frames,
+        }
+    }
+
+    pub fn generate_strip(&mut self){
+        for (i,colors) in self.frames.iter().enumerate() {
+            let total:f32 = colors.iter().map(|c| c.1).sum();
+            let mut y_start = 0;
+            for (index,(rgb,share)) in colors.iter().enumerate() {
+                let mut y_end = y_start + (share / total * self.height as f32).round() as u32;
+                if index == colors.len() - 1 {
+                    y_end = self.height;
+                }
+                for x in i as u32 * self.column_width..(i as u32 + 1) * self.column_width {
+                    for y in y_start..y_end.min(self.height) {
+                        self.img.put_pixel(x, y, *rgb);
+                    }
+                }
+                y_start = y_end.min(self.height);
+            }
+        }
+    }
+
+    pub fn save_img(&self,path:&str) -> image::ImageResult<()> {
+        self.img.save(path)
+    }
+}
 #[derive(Default,PartialEq)]
 pub enum DisplayOption {
     GrayScale(Option<egui::TextureHandle>), 
     DefaultWithMarker(Option<egui::TextureHandle>),
+    ClusterMask(Option<egui::TextureHandle>),
+    PaletteMapped(Option<egui::TextureHandle>),
+    DeltaEHeatmap(Option<egui::TextureHandle>),
+    BackgroundMask(Option<egui::TextureHandle>),
     #[default]
     Default,
 }
@@ -353,7 +432,11 @@ impl Display for DisplayOption{
         match *self {
             Self::Default => write!(f,"default"),
             Self::GrayScale(..) => write!(f,"gray_scale"),
-            Self::DefaultWithMarker(..) => write!(f,"marked_default")
+            Self::DefaultWithMarker(..) => write!(f,"marked_default"),
+            Self::ClusterMask(..) => write!(f,"cluster_mask"),
+            Self::PaletteMapped(..) => write!(f,"palette_mapped"),
+            Self::DeltaEHeatmap(..) => write!(f,"delta_e_heatmap"),
+            Self::BackgroundMask(..) => write!(f,"background_mask"),
         }
     }
 }
@@ -362,14 +445,13 @@ pub struct ImageEditor {
     pub img:RgbaImage,
     pub img_width:u32,
     pub img_hight:u32,
-    pub original_img_path:PathBuf,
+    pub original_img_path:Option<PathBuf>,
     pub display_selection:DisplayOption,
     pub image_reader:DynamicImage,
     pub markers:Vec<RGBMarker>
 }
 impl ImageEditor {
-    pub fn new(path:PathBuf) -> Self{
-        let image_reader = ImageReader::open(path.clone()).unwrap().decode().unwrap(); 
+    pub fn new(image_reader:DynamicImage,path:Option<PathBuf>) -> Self{
         let img_hight = image_reader.height();
         let img_width = image_reader.width();
         let img = RgbaImage::new(image_reader.width(),image_reader.height());
@@ -403,9 +485,10 @@ impl ImageEditor {
         self.display_selection = DisplayOption::GrayScale(Some(ui.ctx().load_texture("color_text",ColorImage::from_rgba_premultiplied([self.img_width as usize,self.img_hight as usize],&self.img),egui::TextureOptions::NEAREST)));
     }
 
-    pub fn generate_default_with_markers(&mut self,ui:&mut egui::Ui,img_size:[u32;2],colors:HashMap<u32,AvarageRgb>){
+    pub fn generate_default_with_markers(&mut self,ui:&mut egui::Ui,img_size:[u32;2],colors:HashMap<u32,PaletteColor>){
         let size = (img_size[0]*img_size[1]).isqrt()/20;
         let border_size = size/5;
+        self.markers.clear();
         for (_,c) in colors.iter(){
             if c.marked {
                 let marker = RGBMarker::from_avarage_rgb(c.clone(),size,border_size);
@@ -425,8 +508,87 @@ impl ImageEditor {
         self.display_selection = DisplayOption::DefaultWithMarker(Some(ui.ctx().load_texture("color_text",ColorImage::from_rgba_premultiplied([self.img_width as usize,self.img_hight as usize],&self.img),egui::TextureOptions::NEAREST)));
     }
 
-    pub fn save_img(&self){
-        let file_name = self.original_img_path.file_name().unwrap().to_str();
-        let _ = self.img.save(format!( "./created_images/{}_{}.png",file_name.unwrap_or("unnamed"),self.display_selection));
+    // mask holds one entry per pixel, row by row
+    pub fn generate_cluster_mask(&mut self,ui:&mut egui::Ui,mask:&[bool],style:MaskStyle){
+        let highlight = [255,0,255];
+        let selected = |x:u32,y:u32| mask.get((y * self.img_width + x) as usize).copied().unwrap_or(false);
+        for x in 0..self.img_width {
+            for y in 0..self.img_hight {
+                let mut pixel = self.image_reader.get_pixel(x, y);
+                match style {
+                    MaskStyle::Dim => {
+                        if !selected(x,y) {
+                            for c in pixel.0[0..3].iter_mut() {
+                                *c = (*c as f32 * 0.15 + 20.0) as u8;
+                            }
+                        }
+                    }
+                    MaskStyle::Outline => {
+                        if selected(x,y) {
+                            let edge = x == 0 || y == 0 || x + 1 == self.img_width || y + 1 == self.img_hight
+                                || !selected(x - 1,y) || !selected(x + 1,y) || !selected(x,y - 1) || !selected(x,y + 1);
+                            for (c,h) in pixel.0[0..3].iter_mut().zip(highlight) {
+                                *c = if edge {h} else {(*c as f32 * 0.6 + h as f32 * 0.4) as u8};
+                            }
+                            pixel.0[3] = 255;
+                        }
+                    }
+                }
+                self.img.put_pixel(x, y, pixel);
+            }
+        }
+        let image = ColorImage::from_rgba_unmultiplied([self.img_width as usize,self.img_hight as usize],&self.img);
+        self.display_selection = DisplayOption::ClusterMask(Some(ui.ctx().load_texture("cluster_mask",image,egui::TextureOptions::NEAREST)));
+    }
+
+    pub fn generate_palette_mapped_img(&mut self,ui:&mut egui::Ui,palette:&[Rgb<u8>],space:iris_color::ColorSpace,dither:iris_dither::DitherMode){
+        self.img = iris_dither::palette_map(&self.image_reader,palette,space,dither);
+        let image = ColorImage::from_rgba_unmultiplied([self.img_width as usize,self.img_hight as usize],&self.img);
+        self.display_selection = DisplayOption::PaletteMapped(Some(ui.ctx().load_texture("palette_mapped",image,egui::TextureOptions::NEAREST)));
+    }
+
+    // delta_e holds one value per pixel, row by row
+    pub fn generate_delta_e_heatmap(&mut self,ui:&mut egui::Ui,delta_e:&[f32],max:f32){
+        for x in 0..self.img_width {
+            for y in 0..self.img_hight {
+                let alpha = self.image_reader.get_pixel(x, y).0[3];
+                let value = delta_e.get((y * self.img_width + x) as usize).copied().unwrap_or(0.0);
+                let [r,g,b] = iris_metrics::heat_color(value,max);
+                self.img.put_pixel(x, y, image::Rgba([r,g,b,alpha]));
+            }
+        }
+        let image = ColorImage::from_rgba_unmultiplied([self.img_width as usize,self.img_hight as usize],&self.img);
+        self.display_selection = DisplayOption::DeltaEHeatmap(Some(ui.ctx().load_texture("delta_e_heatmap",image,egui::TextureOptions::NEAREST)));
+    }
+
+    // background pixels are covered in a checkerboard, so what is left stands out
+    pub fn generate_background_mask(&mut self,ui:&mut egui::Ui,background:&[bool]){
+        for x in 0..self.img_width {
+            for y in 0..self.img_hight {
+                let mut pixel = self.image_reader.get_pixel(x, y);
+                if background.get((y * self.img_width + x) as usize).copied().unwrap_or(false) {
+                    let shade = if (x / 4 + y / 4) % 2 == 0 {90} else {150};
+                    pixel = image::Rgba([shade,shade,shade,255]);
+                }
+                self.img.put_pixel(x, y, pixel);
+            }
+        }
+        let image = ColorImage::from_rgba_unmultiplied([self.img_width as usize,self.img_hight as usize],&self.img);
+        self.display_selection = DisplayOption::BackgroundMask(Some(ui.ctx().load_texture("background_mask",image,egui::TextureOptions::NEAREST)));
+    }
+
+    // white where the mask is set, black everywhere else
+    pub fn save_cluster_mask(&self,mask:&[bool],path:&str) -> image::ImageResult<()> {
+        let img = image::GrayImage::from_fn(self.img_width,self.img_hight,|x,y| {
+            image::Luma([if mask.get((y * self.img_width + x) as usize).copied().unwrap_or(false) {255} else {0}])
+        });
+        img.save(path)
+    }
+
+    pub fn save_img(&self) -> Result<(),String>{
+        let file_name = self.original_img_path.as_ref().and_then(|p| p.file_name()).and_then(|n| n.to_str());
+        let file = format!( "./created_images/{}_{}.png",file_name.unwrap_or("unnamed"),self.display_selection);
+        let _ = std::fs::create_dir_all("./created_images");
+        self.img.save(&file).map_err(|err| format!("{}: {}",file,err))
     }
 }
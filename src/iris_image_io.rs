@@ -0,0 +1,253 @@
+use std::fmt;
+use std::io::Cursor;
+use std::path::Path;
+use image::{AnimationDecoder, DynamicImage, ExtendedColorType, Frame, ImageDecoder, ImageFormat, ImageReader};
+use image::codecs::{gif::GifDecoder, png::PngDecoder, webp::WebPDecoder};
+
+#[derive(Debug)]
+pub enum ImageLoadError {
+    Io(std::io::Error),
+    NotAFile,
+    Truncated(ImageFormat),
+    // recognised, but this build has no decoder for it
+    UnsupportedFormat(ImageFormat),
+    Decode(image::ImageError),
+}
+
+impl fmt::Display for ImageLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f,"could not read image: {}",err),
+            Self::NotAFile => write!(f,"not a file"),
+            Self::Truncated(format) => write!(f,"{:?} data is truncated",format),
+            Self::UnsupportedFormat(ImageFormat::Avif) => write!(f,"AVIF images need a build with the avif feature (cargo build --features avif)"),
+            Self::UnsupportedFormat(format) => write!(f,"{:?} images can't be opened by this build",format),
+            Self::Decode(image::ImageError::Unsupported(err)) => write!(f,"unsupported image: {}",err),
+            Self::Decode(err) => write!(f,"could not decode image: {}",err),
+        }
+    }
+}
+
+impl From<std::io::Error> for ImageLoadError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<image::ImageError> for ImageLoadError {
+    fn from(err: image::ImageError) -> Self {
+        match err {
+            image::ImageError::IoError(err) => Self::Io(err),
+            err => Self::Decode(err),
+        }
+    }
+}
+
+pub struct LoadedImage {
+    pub image:DynamicImage,
+    pub format:ImageFormat,
+    // color type of the file, before it was expanded for decoding (e.g. 1 bit gray or paletted)
+    pub color_type:ExtendedColorType,
+    // every frame of an animation, composited to the full canvas. empty for still images
+    pub frames:Vec<Frame>,
+}
+
+impl LoadedImage {
+    pub fn format_name(&self) -> String {
+        self.format.extensions_str().first().map(|e| e.to_uppercase()).unwrap_or_else(|| format!("{:?}",self.format))
+    }
+    pub fn bit_depth(&self) -> u16 {
+        self.color_type.bits_per_pixel() / self.color_type.channel_count().max(1) as u16
+    }
+    pub fn channel_name(&self) -> &'static str {
+        match self.color_type.channel_count() {
+            1 => "Gray",
+            2 => "Gray + Alpha",
+            3 => "RGB",
+            _ => "RGBA",
+        }
+    }
+}
+
+// avif is decoded by the system dav1d, only linked with the avif feature. without it the image crate
+// still reports avif as readable, because of its encoder
+pub fn can_decode(format:ImageFormat) -> bool {
+    format.reading_enabled() && (format != ImageFormat::Avif || cfg!(feature = "avif"))
+}
+
+pub fn load_image(path:&Path) -> Result<LoadedImage,ImageLoadError> {
+    if !path.is_file() {
+        return Err(ImageLoadError::NotAFile);
+    }
+    load_image_from_memory(&std::fs::read(path)?,&path.to_string_lossy())
+}
+
+// the format is guessed from the content, so files with a missing or wrong extension still open.
+// formats without a signature (tga) fall back to the extension of the name
+pub fn load_image_from_memory(bytes:&[u8],name:&str) -> Result<LoadedImage,ImageLoadError> {
+    let format = match image::guess_format(bytes) {
+        Ok(format) => format,
+        Err(err) => ImageFormat::from_path(name).map_err(|_| err)?,
+    };
+    if !can_decode(format) {
+        return Err(ImageLoadError::UnsupportedFormat(format));
+    }
+    // the jpeg decoder fills missing scanlines with gray instead of failing, which would skew every scan.
+    // exif and jfif thumbnails have their own end marker, so only the end of the data counts, padding after it is skipped
+    let end = bytes.iter().rposition(|b| *b != 0x00 && *b != 0xFF).map_or(0,|i| i + 1);
+    if format == ImageFormat::Jpeg && !bytes[..end].ends_with(&[0xFF,0xD9]) {
+        return Err(ImageLoadError::Truncated(format));
+    }
+    let decoder = ImageReader::with_format(Cursor::new(bytes),format).into_decoder()?;
+    let color_type = decoder.original_color_type();
+    // animations are decoded once, the first frame is the image
+    let mut frames = decode_frames(bytes,format)?;
+    let image = match frames.first() {
+        Some(frame) => DynamicImage::ImageRgba8(frame.buffer().clone()),
+        None => DynamicImage::from_decoder(decoder)?,
+    };
+    if frames.len() < 2 {
+        frames.clear();
+    }
+    Ok(LoadedImage{image,format,color_type,frames})
+}
+
+fn decode_frames(bytes:&[u8],format:ImageFormat) -> Result<Vec<Frame>,ImageLoadError> {
+    let frames = match format {
+        ImageFormat::Gif => GifDecoder::new(Cursor::new(bytes))?.into_frames().collect_frames()?,
+        ImageFormat::Png => {
+            let decoder = PngDecoder::new(Cursor::new(bytes))?;
+            if !decoder.is_apng()? {
+                return Ok(vec![]);
+            }
+            decoder.apng()?.into_frames().collect_frames()?
+        },
+        ImageFormat::WebP => {
+            let decoder = WebPDecoder::new(Cursor::new(bytes))?;
+            if !decoder.has_animation() {
+                return Ok(vec![]);
+            }
+            decoder.into_frames().collect_frames()?
+        },
+        _ => vec![],
+    };
+    Ok(frames)
+}
+
+// long file names are shortened to keep window titles small
+pub fn window_name(file_name:&str) -> String {
+    let path = Path::new(file_name);
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    if stem.chars().count() < 10 {
+        return file_name.to_string();
+    }
+    let short:String = stem.chars().take(10).collect();
+    match path.extension() {
+        Some(ext) => short + "." + &ext.to_string_lossy(),
+        None => short,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoded(format:ImageFormat) -> Vec<u8> {
+        let img = image::RgbImage::from_fn(32,32,|x,y| image::Rgb([(x*8) as u8,(y*8) as u8,128]));
+        let mut bytes = Cursor::new(vec![]);
+        DynamicImage::ImageRgb8(img).write_to(&mut bytes,format).unwrap();
+        bytes.into_inner()
+    }
+
+    #[test]
+    fn complete_images_load() {
+        for format in [ImageFormat::Png,ImageFormat::Jpeg,ImageFormat::WebP,ImageFormat::Gif,ImageFormat::Tiff,ImageFormat::Bmp,ImageFormat::Tga,ImageFormat::Qoi] {
+            let loaded = load_image_from_memory(&encoded(format),&format!("image.{}",format.extensions_str()[0])).unwrap();
+            assert_eq!((loaded.image.width(),loaded.image.height()),(32,32));
+            assert_eq!(loaded.format,format);
+            assert_eq!(loaded.bit_depth(),8);
+        }
+    }
+
+    #[test]
+    fn truncated_images_fail() {
+        for format in [ImageFormat::Png,ImageFormat::Jpeg] {
+            let bytes = encoded(format);
+            for len in [0,8,bytes.len()/2,bytes.len()-16] {
+                assert!(load_image_from_memory(&bytes[..len],"truncated").is_err(),"{:?} truncated to {} bytes",format,len);
+            }
+        }
+        // a thumbnail in an exif segment right after the start marker brings its own end marker
+        let main = encoded(ImageFormat::Jpeg);
+        let mut payload = b"Exif\0\0".to_vec();
+        payload.extend(encoded(ImageFormat::Jpeg));
+        let mut bytes = main[..2].to_vec();
+        bytes.extend([0xFF,0xE1]);
+        bytes.extend(((payload.len() + 2) as u16).to_be_bytes());
+        bytes.extend(payload);
+        bytes.extend(&main[2..]);
+        assert!(load_image_from_memory(&bytes,"thumbnail.jpg").is_ok());
+        let mut padded = bytes.clone();
+        padded.extend([0;16]);
+        assert!(load_image_from_memory(&padded,"padded.jpg").is_ok());
+        for len in [bytes.len() - main.len()/2,bytes.len() - 16] {
+            assert!(load_image_from_memory(&bytes[..len],"thumbnail.jpg").is_err(),"thumbnail jpeg truncated to {} bytes",len);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "avif")]
+    fn avif_loads() {
+        let loaded = load_image_from_memory(&encoded(ImageFormat::Avif),"photo.avif").unwrap();
+        assert_eq!((loaded.image.width(),loaded.image.height(),loaded.format),(32,32,ImageFormat::Avif));
+    }
+
+    #[test]
+    #[cfg(not(feature = "avif"))]
+    fn avif_points_to_the_feature() {
+        let err = load_image_from_memory(&encoded(ImageFormat::Avif),"photo.avif").err().unwrap();
+        assert!(matches!(err,ImageLoadError::UnsupportedFormat(ImageFormat::Avif)),"{:?}",err);
+        assert!(err.to_string().contains("--features avif"));
+    }
+
+    #[test]
+    fn truncated_file_fails() {
+        let dir = std::env::temp_dir().join(format!("iris_image_io_{}",std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let bytes = encoded(ImageFormat::Png);
+        let path = dir.join("truncated.png");
+        std::fs::write(&path,&bytes[..bytes.len()/2]).unwrap();
+        assert!(load_image(&path).is_err());
+        assert!(matches!(load_image(&dir),Err(ImageLoadError::NotAFile)));
+        let no_extension = dir.join("no_extension");
+        std::fs::write(&no_extension,&bytes).unwrap();
+        assert!(load_image(&no_extension).is_ok());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn animated_gif_frames() {
+        let mut bytes = vec![];
+        {
+            let mut encoder = image::codecs::gif::GifEncoder::new(&mut bytes);
+            for shade in [0,128,255] {
+                let frame = image::RgbaImage::from_pixel(8,8,image::Rgba([shade,0,0,255]));
+                encoder.encode_frame(Frame::new(frame)).unwrap();
+            }
+        }
+        let loaded = load_image_from_memory(&bytes,"anim.gif").unwrap();
+        assert_eq!(loaded.frames.len(),3);
+        assert_eq!(loaded.frames[2].buffer().get_pixel(0,0).0,[255,0,0,255]);
+        assert_eq!(loaded.image.to_rgba8(),*loaded.frames[0].buffer());
+        let still = load_image_from_memory(&encoded(ImageFormat::Gif),"still.gif").unwrap();
+        assert!(still.frames.is_empty());
+    }
+
+    #[test]
+    fn window_names() {
+        assert_eq!(window_name("cat.png"),"cat.png");
+        assert_eq!(window_name("a_very_long_name.png"),"a_very_lon.png");
+        assert_eq!(window_name("no_extension_at_all"),"no_extensi");
+        assert_eq!(window_name("ääääääääääää.jpg"),"ääääääääää.jpg");
+    }
+}
@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+use image::{Rgb, RgbaImage};
+
+use super::iris_color;
+
+// how far a palette mapped image is from the original, transparent pixels are left out
+#[derive(Debug,Clone,Copy,Default)]
+pub struct QualityMetrics {
+    pub mean_oklab:f32,
+    pub p95_oklab:f32,
+    pub max_oklab:f32,
+    pub mean_de2000:f32,
+    pub p95_de2000:f32,
+    pub max_de2000:f32,
+    // infinite if both images are identical
+    pub psnr:f32,
+    pub ssim:f32,
+}
+
+// mean, 95th percentile and max
+fn summary(values:&mut [f32]) -> (f32,f32,f32) {
+    if values.is_empty() {
+        return (0.0,0.0,0.0);
+    }
+    values.sort_by(|a,b| a.total_cmp(b));
+    let mean = values.iter().map(|v| *v as f64).sum::<f64>() / values.len() as f64;
+    let p95 = values[((values.len() - 1) as f32 * 0.95).round() as usize];
+    (mean as f32,p95,values[values.len() - 1])
+}
+
+// also returns the OkLab delta E of every pixel, row by row, 0 for transparent pixels
+pub fn measure(original:&RgbaImage,mapped:&RgbaImage) -> (QualityMetrics,Vec<f32>) {
+    let mut cache:HashMap<([u8;3],[u8;3]),(f32,f32)> = HashMap::new();
+    let mut delta_e_map = Vec::with_capacity((original.width() * original.height()) as usize);
+    let mut oklab = vec![];
+    let mut de2000 = vec![];
+    let mut squared_error = 0.0_f64;
+    for (a,b) in original.pixels().zip(mapped.pixels()) {
+        if a.0[3] == 0 {
+            delta_e_map.push(0.0);
+            continue;
+        }
+        let rgb_a = [a.0[0],a.0[1],a.0[2]];
+        let rgb_b = [b.0[0],b.0[1],b.0[2]];
+        let (ok,de) = *cache.entry((rgb_a,rgb_b)).or_insert_with(|| {
+            let ok = iris_color::OkLab::from_rgb(&Rgb(rgb_a)).distance_to_lab(&iris_color::OkLab::from_rgb(&Rgb(rgb_b)));
+            let de = iris_color::CieLab::from_rgb(Rgb(rgb_a)).delta_e_2000(&iris_color::CieLab::from_rgb(Rgb(rgb_b)));
+            (ok,de)
+        });
+        delta_e_map.push(ok);
+        oklab.push(ok);
+        de2000.push(de);
+        for (ca,cb) in rgb_a.iter().zip(rgb_b) {
+            squared_error += (*ca as f64 - cb as f64).powi(2);
+        }
+    }
+    let mse = squared_error / (oklab.len().max(1) * 3) as f64;
+    let psnr = if mse == 0.0 {f32::INFINITY} else {(10.0 * (255.0_f64 * 255.0 / mse).log10()) as f32};
+    let (mean_oklab,p95_oklab,max_oklab) = summary(&mut oklab);
+    let (mean_de2000,p95_de2000,max_de2000) = summary(&mut de2000);
+    let metrics = QualityMetrics{mean_oklab,p95_oklab,max_oklab,mean_de2000,p95_de2000,max_de2000,psnr,ssim:ssim(original,mapped)};
+    (metrics,delta_e_map)
+}
+
+fn luma(image:&RgbaImage) -> Vec<f64> {
+    image.pixels().map(|p| 0.299 * p.0[0] as f64 + 0.587 * p.0[1] as f64 + 0.114 * p.0[2] as f64).collect()
+}
+
+// structural similarity of the luma, averaged over 8x8 windows that overlap by half
+pub fn ssim(original:&RgbaImage,mapped:&RgbaImage) -> f32 {
+    const WINDOW:u32 = 8;
+    const C1:f64 = (0.01 * 255.0) * (0.01 * 255.0);
+    const C2:f64 = (0.03 * 255.0) * (0.03 * 255.0);
+    let (width,height) = original.dimensions();
+    let (a,b) = (luma(original),luma(mapped));
+    let window_w = WINDOW.min(width);
+    let window_h = WINDOW.min(height);
+    let mut sum = 0.0;
+    let mut count = 0;
+    let mut y = 0;
+    while y + window_h <= height {
+        let mut x = 0;
+        while x + window_w <= width {
+            let n = (window_w * window_h) as f64;
+            let (mut mean_a,mut mean_b) = (0.0,0.0);
+            for wy in y..y + window_h {
+                for wx in x..x + window_w {
+                    let i = (wy * width + wx) as usize;
+                    mean_a += a[i];
+                    mean_b += b[i];
+                }
+            }
+            mean_a /= n;
+            mean_b /= n;
+            let (mut var_a,mut var_b,mut covar) = (0.0,0.0,0.0);
+            for wy in y..y + window_h {
+                for wx in x..x + window_w {
+                    let i = (wy * width + wx) as usize;
+                    var_a += (a[i] - mean_a).powi(2);
+                    var_b += (b[i] - mean_b).powi(2);
+                    covar += (a[i] - mean_a) * (b[i] - mean_b);
+                }
+            }
+            let samples = (n - 1.0).max(1.0);
+            var_a /= samples;
+            var_b /= samples;
+            covar /= samples;
+            sum += ((2.0 * mean_a * mean_b + C1) * (2.0 * covar + C2)) / ((mean_a * mean_a + mean_b * mean_b + C1) * (var_a + var_b + C2));
+            count += 1;
+            x += (window_w / 2).max(1);
+        }
+        y += (window_h / 2).max(1);
+    }
+    if count == 0 {1.0} else {(sum / count as f64) as f32}
+}
+
+// black over red and yellow to white, values above max are white
+pub fn heat_color(value:f32,max:f32) -> [u8;3] {
+    let t = (value / max.max(f32::EPSILON)).clamp(0.0,1.0) * 3.0;
+    let channel = |c:f32| (c.clamp(0.0,1.0) * 255.0).round() as u8;
+    [channel(t),channel(t - 1.0),channel(t - 2.0)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn identical_images() {
+        let image = RgbaImage::from_fn(12,10,|x,y| Rgba([(x * 20) as u8,(y * 25) as u8,90,255]));
+        let (metrics,delta_e_map) = measure(&image,&image);
+        assert_eq!(metrics.ssim,1.0);
+        assert_eq!(metrics.psnr,f32::INFINITY);
+        assert_eq!((metrics.mean_oklab,metrics.max_oklab,metrics.max_de2000),(0.0,0.0,0.0));
+        assert!(delta_e_map.iter().all(|d| *d == 0.0));
+    }
+
+    #[test]
+    fn percentile_max_and_psnr() {
+        // 20 opaque pixels, each one further off than the last, and one transparent pixel
+        let original = RgbaImage::from_fn(21,1,|x,_| Rgba([100,100,100,if x == 20 {0} else {255}]));
+        let mapped = RgbaImage::from_fn(21,1,|x,_| Rgba([100 + (x * 4) as u8,100,100,255]));
+        let (metrics,delta_e_map) = measure(&original,&mapped);
+        let oklab = |x:u32| iris_color::OkLab::from_rgb(&Rgb([100,100,100])).distance_to_lab(&iris_color::OkLab::from_rgb(&Rgb([100 + (x * 4) as u8,100,100])));
+        assert_eq!(delta_e_map[20],0.0);
+        assert_eq!(delta_e_map[7],oklab(7));
+        // the 95th percentile of 20 sorted values is the 19th
+        assert_eq!(metrics.p95_oklab,oklab(18));
+        assert_eq!(metrics.max_oklab,oklab(19));
+        let mean = (0..20).map(oklab).sum::<f32>() / 20.0;
+        assert!((metrics.mean_oklab - mean).abs() < 1e-6);
+        // only red is off, the squared error is averaged over all three channels
+        let mse = (0..20).map(|x| (x * 4) as f64 * (x * 4) as f64).sum::<f64>() / 60.0;
+        assert!((metrics.psnr as f64 - 10.0 * (255.0 * 255.0 / mse).log10()).abs() < 1e-3);
+        assert!(metrics.ssim < 1.0);
+    }
+}
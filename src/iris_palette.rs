@@ -0,0 +1,740 @@
+use std::fmt;
+use std::path::Path;
+use image::{GenericImageView, Pixel, Rgb};
+
+use super::iris_color;
+
+#[derive(Debug)]
+pub enum PaletteError {
+    Io(std::io::Error),
+    UnsupportedFormat(String),
+    Malformed{format:&'static str,reason:String},
+    Empty(&'static str),
+}
+
+impl fmt::Display for PaletteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f,"could not read palette file: {}",err),
+            Self::UnsupportedFormat(ext) => write!(f,"unsupported palette format \"{}\" (expected gpl, ase, aco, pal, hex, txt or png)",ext),
+            Self::Malformed{format,reason} => write!(f,"malformed {} palette: {}",format,reason),
+            Self::Empty(format) => write!(f,"{} palette contains no colors",format),
+        }
+    }
+}
+
+impl From<std::io::Error> for PaletteError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+fn malformed(format:&'static str,reason:impl Into<String>) -> PaletteError {
+    PaletteError::Malformed{format,reason:reason.into()}
+}
+
+pub fn import_palette(path:&Path) -> Result<Vec<Rgb<u8>>,PaletteError> {
+    let ext = path.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+    let colors = match ext.as_str() {
+        "png" => parse_png_strip(&std::fs::read(path)?)?,
+        "gpl" => parse_gpl(&std::fs::read_to_string(path)?)?,
+        "ase" => parse_ase(&std::fs::read(path)?)?,
+        "aco" => parse_aco(&std::fs::read(path)?)?,
+        "pal" => parse_pal(&std::fs::read(path)?)?,
+        "hex" | "txt" => parse_hex_list(&std::fs::read_to_string(path)?)?,
+        _ => return Err(PaletteError::UnsupportedFormat(ext)),
+    };
+    Ok(colors)
+}
+
+pub fn is_palette_file(path:&Path) -> bool {
+    let ext = path.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+    matches!(ext.as_str(),"gpl" | "ase" | "aco" | "pal" | "hex")
+}
+
+pub fn parse_gpl(text:&str) -> Result<Vec<Rgb<u8>>,PaletteError> {
+    let mut lines = text.lines();
+    match lines.next() {
+        Some(header) if header.trim() == "GIMP Palette" => {},
+        _ => return Err(malformed("GPL","missing \"GIMP Palette\" header")),
+    }
+    let mut colors = vec![];
+    for (line_n,line) in lines.enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with("Name:") || line.starts_with("Columns:") {
+            continue;
+        }
+        let mut values = line.split_whitespace();
+        let mut rgb = [0_u8;3];
+        for channel in rgb.iter_mut() {
+            let value = values.next().ok_or_else(|| malformed("GPL",format!("line {} has fewer than three values",line_n + 2)))?;
+            *channel = value.parse().map_err(|_| malformed("GPL",format!("line {}: \"{}\" is not a value between 0 and 255",line_n + 2,value)))?;
+        }
+        colors.push(Rgb::from(rgb));
+    }
+    if colors.is_empty() {
+        return Err(PaletteError::Empty("GPL"));
+    }
+    Ok(colors)
+}
+
+pub fn parse_hex_list(text:&str) -> Result<Vec<Rgb<u8>>,PaletteError> {
+    let mut colors = vec![];
+    for (line_n,line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') || line.starts_with("//") {
+            continue;
+        }
+        let hex = line.split_whitespace().next().unwrap_or_default();
+        colors.push(parse_hex_color(hex).ok_or_else(|| malformed("hex",format!("line {}: \"{}\" is not a hex color",line_n + 1,hex)))?);
+    }
+    if colors.is_empty() {
+        return Err(PaletteError::Empty("hex"));
+    }
+    Ok(colors)
+}
+
+pub fn parse_hex_color(hex:&str) -> Option<Rgb<u8>> {
+    let hex = hex.trim_start_matches('#');
+    let hex = hex.strip_prefix("0x").unwrap_or(hex);
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    match hex.len() {
+        3 => {
+            let mut rgb = [0_u8;3];
+            for (i,c) in hex.chars().enumerate() {
+                let v = c.to_digit(16)? as u8;
+                rgb[i] = v * 16 + v;
+            }
+            Some(Rgb::from(rgb))
+        },
+        6 | 8 => {
+            let r = u8::from_str_radix(&hex[0..2],16).ok()?;
+            let g = u8::from_str_radix(&hex[2..4],16).ok()?;
+            let b = u8::from_str_radix(&hex[4..6],16).ok()?;
+            Some(Rgb::from([r,g,b]))
+        },
+        _ => None,
+    }
+}
+
+pub fn parse_png_strip(bytes:&[u8]) -> Result<Vec<Rgb<u8>>,PaletteError> {
+    let image = image::load_from_memory_with_format(bytes,image::ImageFormat::Png)
+        .map_err(|err| malformed("PNG",err.to_string()))?;
+    let mut colors:Vec<Rgb<u8>> = vec![];
+    for (_,_,rgba) in image.pixels() {
+        if rgba.channels()[3] == 0 {
+            continue;
+        }
+        let rgb = rgba.to_rgb();
+        if !colors.contains(&rgb) {
+            colors.push(rgb);
+        }
+    }
+    if colors.is_empty() {
+        return Err(PaletteError::Empty("PNG"));
+    }
+    Ok(colors)
+}
+
+struct ByteReader<'a> {
+    bytes:&'a [u8],
+    pos:usize,
+    format:&'static str,
+}
+
+impl<'a> ByteReader<'a> {
+    fn new(bytes:&'a [u8],format:&'static str) -> Self {
+        Self{bytes,pos:0,format}
+    }
+    fn take(&mut self,n:usize) -> Result<&'a [u8],PaletteError> {
+        if self.pos + n > self.bytes.len() {
+            return Err(malformed(self.format,format!("unexpected end of file at byte {}",self.pos)));
+        }
+        let slice = &self.bytes[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(slice)
+    }
+    fn u16_be(&mut self) -> Result<u16,PaletteError> {
+        let b = self.take(2)?;
+        Ok(u16::from_be_bytes([b[0],b[1]]))
+    }
+    fn u16_le(&mut self) -> Result<u16,PaletteError> {
+        let b = self.take(2)?;
+        Ok(u16::from_le_bytes([b[0],b[1]]))
+    }
+    fn u32_be(&mut self) -> Result<u32,PaletteError> {
+        let b = self.take(4)?;
+        Ok(u32::from_be_bytes([b[0],b[1],b[2],b[3]]))
+    }
+    fn u32_le(&mut self) -> Result<u32,PaletteError> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0],b[1],b[2],b[3]]))
+    }
+    fn f32_be(&mut self) -> Result<f32,PaletteError> {
+        Ok(f32::from_bits(self.u32_be()?))
+    }
+    fn is_empty(&self) -> bool {
+        self.pos >= self.bytes.len()
+    }
+}
+
+fn unit_to_u8(v:f32) -> u8 {
+    (v.clamp(0.0,1.0) * 255.0).round() as u8
+}
+
+fn hsv_to_rgb(h:f32,s:f32,v:f32) -> Rgb<u8> {
+    let c = v * s;
+    let x = c * (1.0 - ((h/60.0) % 2.0 - 1.0).abs());
+    let m = v - c;
+    let (r,g,b) = match (h/60.0).floor() as u32 {
+        0 => (c,x,0.0),
+        1 => (x,c,0.0),
+        2 => (0.0,c,x),
+        3 => (0.0,x,c),
+        4 => (x,0.0,c),
+        _ => (c,0.0,x),
+    };
+    Rgb::from([unit_to_u8(r + m),unit_to_u8(g + m),unit_to_u8(b + m)])
+}
+
+fn cmyk_to_rgb(c:f32,m:f32,y:f32,k:f32) -> Rgb<u8> {
+    Rgb::from([
+        unit_to_u8((1.0 - c) * (1.0 - k)),
+        unit_to_u8((1.0 - m) * (1.0 - k)),
+        unit_to_u8((1.0 - y) * (1.0 - k)),
+    ])
+}
+
+pub fn parse_ase(bytes:&[u8]) -> Result<Vec<Rgb<u8>>,PaletteError> {
+    let mut reader = ByteReader::new(bytes,"ASE");
+    if reader.take(4)? != b"ASEF" {
+        return Err(malformed("ASE","missing \"ASEF\" signature"));
+    }
+    let _version = [reader.u16_be()?,reader.u16_be()?];
+    let block_count = reader.u32_be()?;
+    let mut colors = vec![];
+    for _ in 0..block_count {
+        let block_type = reader.u16_be()?;
+        let block_len = reader.u32_be()? as usize;
+        let block = reader.take(block_len)?;
+        if block_type != 0x0001 {
+            // group start/end blocks carry no colors
+            continue;
+        }
+        let mut entry = ByteReader::new(block,"ASE");
+        let name_len = entry.u16_be()? as usize;
+        entry.take(name_len * 2)?;
+        let model = entry.take(4)?;
+        let rgb = match model {
+            b"RGB " => Rgb::from([unit_to_u8(entry.f32_be()?),unit_to_u8(entry.f32_be()?),unit_to_u8(entry.f32_be()?)]),
+            b"CMYK" => cmyk_to_rgb(entry.f32_be()?,entry.f32_be()?,entry.f32_be()?,entry.f32_be()?),
+            b"Gray" => {
+                let v = unit_to_u8(entry.f32_be()?);
+                Rgb::from([v,v,v])
+            },
+            b"LAB " => {
+                let l = entry.f32_be()? * 100.0;
+                let a = entry.f32_be()?;
+                let b = entry.f32_be()?;
+                iris_color::CieLab::new(l,a,b).to_rgb()
+            },
+            other => return Err(malformed("ASE",format!("unknown color model \"{}\"",String::from_utf8_lossy(other)))),
+        };
+        colors.push(rgb);
+    }
+    if colors.is_empty() {
+        return Err(PaletteError::Empty("ASE"));
+    }
+    Ok(colors)
+}
+
+pub fn parse_aco(bytes:&[u8]) -> Result<Vec<Rgb<u8>>,PaletteError> {
+    let mut reader = ByteReader::new(bytes,"ACO");
+    let mut colors = parse_aco_section(&mut reader)?;
+    // a version 1 section may be followed by a version 2 section holding the same colors plus names
+    if !reader.is_empty() {
+        colors = parse_aco_section(&mut reader)?;
+    }
+    if colors.is_empty() {
+        return Err(PaletteError::Empty("ACO"));
+    }
+    Ok(colors)
+}
+
+fn parse_aco_section(reader:&mut ByteReader) -> Result<Vec<Rgb<u8>>,PaletteError> {
+    let version = reader.u16_be()?;
+    if version != 1 && version != 2 {
+        return Err(malformed("ACO",format!("unknown version {}",version)));
+    }
+    let count = reader.u16_be()?;
+    let mut colors = vec![];
+    for _ in 0..count {
+        let space = reader.u16_be()?;
+        let w = reader.u16_be()?;
+        let x = reader.u16_be()?;
+        let y = reader.u16_be()?;
+        let z = reader.u16_be()?;
+        if version == 2 {
+            let name_len = reader.u32_be()? as usize;
+            reader.take(name_len * 2)?;
+        }
+        let unit = |v:u16| v as f32 / 65535.0;
+        let rgb = match space {
+            0 => Rgb::from([unit_to_u8(unit(w)),unit_to_u8(unit(x)),unit_to_u8(unit(y))]),
+            1 => hsv_to_rgb(unit(w) * 360.0,unit(x),unit(y)),
+            2 => cmyk_to_rgb(1.0 - unit(w),1.0 - unit(x),1.0 - unit(y),1.0 - unit(z)),
+            7 => iris_color::CieLab::new(w as f32/100.0,x as i16 as f32/100.0,y as i16 as f32/100.0).to_rgb(),
+            8 => {
+                let v = unit_to_u8(1.0 - w as f32/10000.0);
+                Rgb::from([v,v,v])
+            },
+            other => return Err(malformed("ACO",format!("unsupported color space {}",other))),
+        };
+        colors.push(rgb);
+    }
+    Ok(colors)
+}
+
+pub fn parse_pal(bytes:&[u8]) -> Result<Vec<Rgb<u8>>,PaletteError> {
+    if bytes.starts_with(b"RIFF") {
+        return parse_riff_pal(bytes);
+    }
+    let text = std::str::from_utf8(bytes).map_err(|_| malformed("PAL","neither a RIFF nor a JASC-PAL file"))?;
+    let mut lines = text.lines().map(|l| l.trim());
+    if lines.next() != Some("JASC-PAL") {
+        return Err(malformed("PAL","missing \"JASC-PAL\" header"));
+    }
+    lines.next().ok_or_else(|| malformed("PAL","missing version line"))?;
+    let count:usize = lines.next()
+        .and_then(|l| l.parse().ok())
+        .ok_or_else(|| malformed("PAL","missing color count"))?;
+    let mut colors = vec![];
+    for (line_n,line) in lines.filter(|l| !l.is_empty()).take(count).enumerate() {
+        let values:Vec<u8> = line.split_whitespace().map(|v| v.parse()).collect::<Result<_,_>>()
+            .map_err(|_| malformed("PAL",format!("color {}: \"{}\" is not three values between 0 and 255",line_n + 1,line)))?;
+        if values.len() < 3 {
+            return Err(malformed("PAL",format!("color {} has fewer than three values",line_n + 1)));
+        }
+        colors.push(Rgb::from([values[0],values[1],values[2]]));
+    }
+    if colors.len() != count {
+        return Err(malformed("PAL",format!("header announces {} colors, found {}",count,colors.len())));
+    }
+    if colors.is_empty() {
+        return Err(PaletteError::Empty("PAL"));
+    }
+    Ok(colors)
+}
+
+fn parse_riff_pal(bytes:&[u8]) -> Result<Vec<Rgb<u8>>,PaletteError> {
+    let mut reader = ByteReader::new(bytes,"RIFF PAL");
+    reader.take(4)?;
+    let _riff_len = reader.u32_le()?;
+    if reader.take(4)? != b"PAL " {
+        return Err(malformed("RIFF PAL","RIFF file is not of type \"PAL \""));
+    }
+    while !reader.is_empty() {
+        let chunk_id = reader.take(4)?;
+        let chunk_len = reader.u32_le()? as usize;
+        let chunk = reader.take(chunk_len)?;
+        if chunk_id != b"data" {
+            continue;
+        }
+        let mut data = ByteReader::new(chunk,"RIFF PAL");
+        let _version = data.u16_le()?;
+        let count = data.u16_le()?;
+        let mut colors = vec![];
+        for _ in 0..count {
+            let entry = data.take(4)?;
+            colors.push(Rgb::from([entry[0],entry[1],entry[2]]));
+        }
+        if colors.is_empty() {
+            return Err(PaletteError::Empty("RIFF PAL"));
+        }
+        return Ok(colors);
+    }
+    Err(malformed("RIFF PAL","no \"data\" chunk"))
+}
+
+const COLOR_NAMES:[(&str,[u8;3]);32] = [
+    ("black",[0,0,0]),
+    ("charcoal",[54,69,79]),
+    ("gray",[128,128,128]),
+    ("silver",[192,192,192]),
+    ("white",[255,255,255]),
+    ("maroon",[128,0,0]),
+    ("red",[255,0,0]),
+    ("salmon",[250,128,114]),
+    ("pink",[255,192,203]),
+    ("brown",[139,69,19]),
+    ("tan",[210,180,140]),
+    ("orange",[255,165,0]),
+    ("amber",[255,191,0]),
+    ("gold",[255,215,0]),
+    ("yellow",[255,255,0]),
+    ("beige",[245,245,220]),
+    ("olive",[128,128,0]),
+    ("lime",[0,255,0]),
+    ("green",[0,128,0]),
+    ("forest",[34,139,34]),
+    ("mint",[152,255,152]),
+    ("teal",[0,128,128]),
+    ("cyan",[0,255,255]),
+    ("sky",[135,206,235]),
+    ("blue",[0,0,255]),
+    ("navy",[0,0,128]),
+    ("indigo",[75,0,130]),
+    ("violet",[143,0,255]),
+    ("purple",[128,0,128]),
+    ("magenta",[255,0,255]),
+    ("lavender",[230,230,250]),
+    ("crimson",[220,20,60]),
+];
+
+pub fn nearest_color_name(rgb:&Rgb<u8>) -> &'static str {
+    let lab = iris_color::OkLab::from_rgb(rgb);
+    COLOR_NAMES.iter()
+        .min_by(|a,b| {
+            let dist_a = lab.distance_to_lab(&iris_color::OkLab::from_rgb(&Rgb::from(a.1)));
+            let dist_b = lab.distance_to_lab(&iris_color::OkLab::from_rgb(&Rgb::from(b.1)));
+            dist_a.total_cmp(&dist_b)
+        })
+        .map(|(name,_)| *name)
+        .unwrap_or("color")
+}
+
+#[derive(Debug,PartialEq,Clone,Copy,Default)]
+pub enum ExportFormat {
+    #[default]
+    CssHex,
+    CssRgb,
+    CssOklch,
+    Tailwind,
+    Scss,
+    RustConst,
+    Glsl,
+    Hlsl,
+}
+
+impl ExportFormat {
+    pub const ALL:[ExportFormat;8] = [
+        Self::CssHex,
+        Self::CssRgb,
+        Self::CssOklch,
+        Self::Tailwind,
+        Self::Scss,
+        Self::RustConst,
+        Self::Glsl,
+        Self::Hlsl,
+    ];
+    // tells the variants that share an extension apart in file names
+    pub fn slug(&self) -> &'static str {
+        match self {
+            Self::CssHex => "css_hex",
+            Self::CssRgb => "css_rgb",
+            Self::CssOklch => "css_oklch",
+            Self::Tailwind => "tailwind",
+            Self::Scss => "scss",
+            Self::RustConst => "rust",
+            Self::Glsl => "glsl",
+            Self::Hlsl => "hlsl",
+        }
+    }
+    pub fn file_extension(&self) -> &'static str {
+        match self {
+            Self::CssHex | Self::CssRgb | Self::CssOklch => "css",
+            Self::Tailwind => "js",
+            Self::Scss => "scss",
+            Self::RustConst => "rs",
+            Self::Glsl => "glsl",
+            Self::Hlsl => "hlsl",
+        }
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CssHex => write!(f,"CSS variables (hex)"),
+            Self::CssRgb => write!(f,"CSS variables (rgb())"),
+            Self::CssOklch => write!(f,"CSS variables (oklch())"),
+            Self::Tailwind => write!(f,"Tailwind colors"),
+            Self::Scss => write!(f,"SCSS map"),
+            Self::RustConst => write!(f,"Rust const array"),
+            Self::Glsl => write!(f,"GLSL vec3 array"),
+            Self::Hlsl => write!(f,"HLSL float3 array"),
+        }
+    }
+}
+
+#[derive(Debug,PartialEq,Clone,Copy,Default)]
+pub enum ExportNaming {
+    Index,
+    #[default]
+    ColorName,
+}
+
+pub fn export_file_name(format:ExportFormat,naming:ExportNaming) -> String {
+    let naming = match naming {
+        ExportNaming::Index => "index",
+        ExportNaming::ColorName => "names",
+    };
+    format!("palette_{}_{}.{}",format.slug(),naming,format.file_extension())
+}
+
+pub fn export_names(colors:&[Rgb<u8>],naming:ExportNaming) -> Vec<String> {
+    let mut names:Vec<String> = vec![];
+    for (i,rgb) in colors.iter().enumerate() {
+        let base = match naming {
+            ExportNaming::Index => format!("color-{}",i + 1),
+            ExportNaming::ColorName => nearest_color_name(rgb).to_string(),
+        };
+        let mut name = base.clone();
+        let mut n = 2;
+        while names.contains(&name) {
+            name = format!("{}-{}",base,n);
+            n += 1;
+        }
+        names.push(name);
+    }
+    names
+}
+
+fn hex(rgb:&Rgb<u8>) -> String {
+    format!("#{:02x}{:02x}{:02x}",rgb.0[0],rgb.0[1],rgb.0[2])
+}
+
+fn oklch(rgb:&Rgb<u8>) -> String {
+    let lab = iris_color::OkLab::from_rgb(rgb);
+    let chroma = (lab.a * lab.a + lab.b * lab.b).sqrt();
+    let hue = if chroma < 0.0001 {0.0} else {lab.b.atan2(lab.a).to_degrees().rem_euclid(360.0)};
+    format!("oklch({:.2}% {:.4} {:.2})",lab.l * 100.0,chroma,hue)
+}
+
+fn unit_vec3(rgb:&Rgb<u8>) -> String {
+    format!("{:.3}, {:.3}, {:.3}",rgb.0[0] as f32/255.0,rgb.0[1] as f32/255.0,rgb.0[2] as f32/255.0)
+}
+
+pub fn export_palette(colors:&[Rgb<u8>],format:ExportFormat,naming:ExportNaming) -> String {
+    let names = export_names(colors,naming);
+    let n = colors.len();
+    let mut out = String::new();
+    match format {
+        ExportFormat::CssHex | ExportFormat::CssRgb | ExportFormat::CssOklch => {
+            out.push_str(":root {\n");
+            for (rgb,name) in colors.iter().zip(names.iter()) {
+                let value = match format {
+                    ExportFormat::CssHex => hex(rgb),
+                    ExportFormat::CssRgb => format!("rgb({} {} {})",rgb.0[0],rgb.0[1],rgb.0[2]),
+                    _ => oklch(rgb),
+                };
+                out.push_str(&format!("  --{}: {};\n",name,value));
+            }
+            out.push_str("}\n");
+        },
+        ExportFormat::Tailwind => {
+            out.push_str("colors: {\n");
+            for (rgb,name) in colors.iter().zip(names.iter()) {
+                out.push_str(&format!("  '{}': '{}',\n",name,hex(rgb)));
+            }
+            out.push_str("},\n");
+        },
+        ExportFormat::Scss => {
+            out.push_str("$palette: (\n");
+            for (rgb,name) in colors.iter().zip(names.iter()) {
+                out.push_str(&format!("  \"{}\": {},\n",name,hex(rgb)));
+            }
+            out.push_str(");\n");
+        },
+        ExportFormat::RustConst => {
+            out.push_str(&format!("pub const PALETTE: [[u8; 3]; {}] = [\n",n));
+            for (rgb,name) in colors.iter().zip(names.iter()) {
+                out.push_str(&format!("    [{}, {}, {}], // {}\n",rgb.0[0],rgb.0[1],rgb.0[2],name));
+            }
+            out.push_str("];\n");
+        },
+        ExportFormat::Glsl => {
+            out.push_str(&format!("const vec3 palette[{}] = vec3[{}](\n",n,n));
+            for (i,(rgb,name)) in colors.iter().zip(names.iter()).enumerate() {
+                let sep = if i + 1 < n {","} else {""};
+                out.push_str(&format!("    vec3({}){} // {}\n",unit_vec3(rgb),sep,name));
+            }
+            out.push_str(");\n");
+        },
+        ExportFormat::Hlsl => {
+            out.push_str(&format!("static const float3 palette[{}] = {{\n",n));
+            for (rgb,name) in colors.iter().zip(names.iter()) {
+                out.push_str(&format!("    float3({}), // {}\n",unit_vec3(rgb),name));
+            }
+            out.push_str("};\n");
+        },
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED:Rgb<u8> = Rgb([255,0,0]);
+    const TEAL:Rgb<u8> = Rgb([0,128,128]);
+
+    fn ase_block(name:&str,model:&[u8;4],values:&[f32]) -> Vec<u8> {
+        let mut block = vec![];
+        let name:Vec<u16> = name.encode_utf16().chain([0]).collect();
+        block.extend((name.len() as u16).to_be_bytes());
+        for c in name {
+            block.extend(c.to_be_bytes());
+        }
+        block.extend(model);
+        for v in values {
+            block.extend(v.to_be_bytes());
+        }
+        // global color type
+        block.extend(0_u16.to_be_bytes());
+        let mut out = vec![];
+        out.extend(0x0001_u16.to_be_bytes());
+        out.extend((block.len() as u32).to_be_bytes());
+        out.extend(block);
+        out
+    }
+
+    fn ase(blocks:&[Vec<u8>]) -> Vec<u8> {
+        let mut out = b"ASEF".to_vec();
+        out.extend([0,1,0,0]);
+        out.extend((blocks.len() as u32).to_be_bytes());
+        for block in blocks {
+            out.extend(block);
+        }
+        out
+    }
+
+    fn riff_pal(chunk_len:Option<u32>,colors:&[[u8;3]]) -> Vec<u8> {
+        let mut data = vec![];
+        data.extend(0x0300_u16.to_le_bytes());
+        data.extend((colors.len() as u16).to_le_bytes());
+        for c in colors {
+            data.extend([c[0],c[1],c[2],0]);
+        }
+        let mut out = b"RIFF".to_vec();
+        out.extend((data.len() as u32 + 12).to_le_bytes());
+        out.extend(b"PAL data");
+        out.extend(chunk_len.unwrap_or(data.len() as u32).to_le_bytes());
+        out.extend(data);
+        out
+    }
+
+    fn is_malformed<T:fmt::Debug>(result:Result<T,PaletteError>) -> bool {
+        matches!(result,Err(PaletteError::Malformed{..}))
+    }
+
+    #[test]
+    fn gpl() {
+        let text = "GIMP Palette\nName: Test\nColumns: 2\n# comment\n255   0   0\tRed\n  0 128 128 Teal\n";
+        assert_eq!(parse_gpl(text).unwrap(),vec![RED,TEAL]);
+        assert!(is_malformed(parse_gpl("255 0 0\n")));
+        assert!(is_malformed(parse_gpl("GIMP Palette\n255 0\n")));
+        assert!(is_malformed(parse_gpl("GIMP Palette\n256 0 0\n")));
+        assert!(matches!(parse_gpl("GIMP Palette\nName: Empty\n"),Err(PaletteError::Empty("GPL"))));
+    }
+
+    #[test]
+    fn hex_list() {
+        assert_eq!(parse_hex_list("; comment\n#ff0000\n008080 teal\n0xF00\n#ff0000ff\n").unwrap(),vec![RED,TEAL,RED,RED]);
+        assert!(is_malformed(parse_hex_list("#ff00\n")));
+        assert!(is_malformed(parse_hex_list("#gg0000\n")));
+        assert!(matches!(parse_hex_list("\n// nothing\n"),Err(PaletteError::Empty("hex"))));
+    }
+
+    #[test]
+    fn png_strip() {
+        let strip = image::RgbaImage::from_fn(4,1,|x,_| match x {
+            0 | 2 => image::Rgba([255,0,0,255]),
+            1 => image::Rgba([0,128,128,255]),
+            _ => image::Rgba([9,9,9,0]),
+        });
+        let mut bytes = std::io::Cursor::new(vec![]);
+        strip.write_to(&mut bytes,image::ImageFormat::Png).unwrap();
+        let bytes = bytes.into_inner();
+        assert_eq!(parse_png_strip(&bytes).unwrap(),vec![RED,TEAL]);
+        assert!(is_malformed(parse_png_strip(&bytes[..bytes.len()/2])));
+    }
+
+    #[test]
+    fn ase_models() {
+        let bytes = ase(&[
+            ase_block("red",b"RGB ",&[1.0,0.0,0.0]),
+            ase_block("gray",b"Gray",&[0.5]),
+            ase_block("black",b"CMYK",&[0.0,0.0,0.0,1.0]),
+        ]);
+        assert_eq!(parse_ase(&bytes).unwrap(),vec![RED,Rgb([128,128,128]),Rgb([0,0,0])]);
+    }
+
+    #[test]
+    fn ase_malformed() {
+        let bytes = ase(&[ase_block("red",b"RGB ",&[1.0,0.0,0.0])]);
+        assert!(is_malformed(parse_ase(b"ASEX\0\x01\0\0\0\0\0\0")));
+        // the block announces more bytes than the file has
+        for len in [4,12,bytes.len() - 1] {
+            assert!(is_malformed(parse_ase(&bytes[..len])),"truncated to {}",len);
+        }
+        assert!(is_malformed(parse_ase(&ase(&[ase_block("odd",b"XYZ ",&[0.0,0.0,0.0])]))));
+        assert!(matches!(parse_ase(&ase(&[])),Err(PaletteError::Empty("ASE"))));
+    }
+
+    #[test]
+    fn aco() {
+        let mut bytes = vec![];
+        for v in [1_u16,2,0,65535,0,0,0,8,5000,0,0,0] {
+            bytes.extend(v.to_be_bytes());
+        }
+        assert_eq!(parse_aco(&bytes).unwrap(),vec![RED,Rgb([128,128,128])]);
+        assert!(is_malformed(parse_aco(&bytes[..bytes.len() - 2])));
+        assert!(is_malformed(parse_aco(&[0,3,0,0])));
+        assert!(is_malformed(parse_aco(&[0,1,0,1,0,9,0,0,0,0,0,0,0,0])));
+        assert!(matches!(parse_aco(&[0,1,0,0]),Err(PaletteError::Empty("ACO"))));
+    }
+
+    #[test]
+    fn jasc_pal() {
+        assert_eq!(parse_pal(b"JASC-PAL\r\n0100\r\n2\r\n255 0 0\r\n0 128 128\r\n").unwrap(),vec![RED,TEAL]);
+        assert!(is_malformed(parse_pal(b"JASC-PAL\n0100\n3\n255 0 0\n")));
+        assert!(is_malformed(parse_pal(b"JASC-PAL\n0100\n1\n255 0\n")));
+        assert!(is_malformed(parse_pal(b"JASC-PAL\n0100\n")));
+        assert!(is_malformed(parse_pal(b"NOT-PAL\n")));
+        assert!(is_malformed(parse_pal(&[0xff,0xfe,0x00])));
+    }
+
+    #[test]
+    fn riff_pal_chunks() {
+        assert_eq!(parse_pal(&riff_pal(None,&[[255,0,0],[0,128,128]])).unwrap(),vec![RED,TEAL]);
+        // chunk sizes past the end of the file
+        assert!(is_malformed(parse_pal(&riff_pal(Some(4096),&[[255,0,0]]))));
+        assert!(is_malformed(parse_pal(&riff_pal(Some(2),&[[255,0,0]]))));
+        let mut not_pal = riff_pal(None,&[[255,0,0]]);
+        not_pal[8..12].copy_from_slice(b"WAVE");
+        assert!(is_malformed(parse_pal(&not_pal)));
+        assert!(matches!(parse_pal(&riff_pal(None,&[])),Err(PaletteError::Empty("RIFF PAL"))));
+    }
+
+    #[test]
+    fn export_file_names_differ() {
+        let mut names:Vec<String> = vec![];
+        for format in ExportFormat::ALL {
+            for naming in [ExportNaming::Index,ExportNaming::ColorName] {
+                let name = export_file_name(format,naming);
+                assert!(name.ends_with(format.file_extension()));
+                assert!(!names.contains(&name),"{}",name);
+                names.push(name);
+            }
+        }
+    }
+
+    #[test]
+    fn import_by_extension() {
+        assert!(matches!(import_palette(Path::new("palette.xyz")),Err(PaletteError::UnsupportedFormat(ext)) if ext == "xyz"));
+        assert!(matches!(import_palette(Path::new("/does/not/exist.gpl")),Err(PaletteError::Io(_))));
+    }
+}
@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+
+use super::{iris_background, iris_color, iris_filter, iris_hdr, iris_scan, iris_selection};
+use super::{AvarageingSystem, CompareState};
+
+pub const PROJECT_EXTENSION:&str = "iris";
+const PROJECT_VERSION:u32 = 1;
+
+#[derive(Debug)]
+pub enum ProjectError {
+    Io(std::io::Error),
+    Format(serde_json::Error),
+    UnsupportedVersion(u32),
+}
+
+impl fmt::Display for ProjectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f,"could not access project file: {}",err),
+            Self::Format(err) => write!(f,"invalid project file: {}",err),
+            Self::UnsupportedVersion(v) => write!(f,"project file version {} is newer than this version of Iris supports ({})",v,PROJECT_VERSION),
+        }
+    }
+}
+
+impl From<std::io::Error> for ProjectError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for ProjectError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Format(err)
+    }
+}
+
+#[derive(Serialize,Deserialize)]
+pub struct ProjectWindow {
+    pub path:PathBuf,
+    pub avaraging_system:AvarageingSystem,
+    pub color_gradation:f32,
+    pub color_dist_type:iris_color::ColorSpace,
+    pub color_display_threshhold:f32,
+    pub clean_up_value:f32,
+    pub median_cut_amount:u32,
+    pub mean_schift_radius:f32,
+    pub compare_state:CompareState,
+    pub mark_every_color:bool,
+    #[serde(default)]
+    pub current_frame:usize,
+    #[serde(default)]
+    pub scan_all_frames:bool,
+    #[serde(default)]
+    pub tone_mapper:iris_hdr::ToneMapper,
+    #[serde(default)]
+    pub exposure:f32,
+    #[serde(default)]
+    pub scan_scene_linear:bool,
+    #[serde(default)]
+    pub median_method:iris_scan::MedianMethod,
+    #[serde(default)]
+    pub selection:Option<iris_selection::Selection>,
+    #[serde(default)]
+    pub background:iris_background::BackgroundSettings,
+    #[serde(default)]
+    pub filter:iris_filter::ColorFilter,
+    #[serde(default)]
+    pub pixel_totals:iris_scan::PixelTotals,
+    pub color_list:HashMap<u32,iris_color::PaletteColor>,
+    pub color_percent:HashMap<u32,f32>,
+    pub color_pixel_count:HashMap<u32,u32>,
+}
+
+#[derive(Serialize,Deserialize)]
+pub struct Project {
+    pub version:u32,
+    pub windows:Vec<ProjectWindow>,
+    pub global_colors:Vec<iris_color::PaletteColor>,
+}
+
+impl Project {
+    pub fn new(windows:Vec<ProjectWindow>,global_colors:Vec<iris_color::PaletteColor>) -> Self {
+        Self{version:PROJECT_VERSION,windows,global_colors}
+    }
+
+    // image paths inside the project folder are stored relative to it, so the project can be shared along with its images
+    pub fn save(mut self,path:&Path) -> Result<(),ProjectError> {
+        let base = project_dir(path);
+        for w in self.windows.iter_mut() {
+            if let Ok(relative) = w.path.strip_prefix(&base) {
+                w.path = relative.to_path_buf();
+            }
+        }
+        std::fs::write(path,serde_json::to_string_pretty(&self)?)?;
+        Ok(())
+    }
+
+    pub fn load(path:&Path) -> Result<Self,ProjectError> {
+        let mut project:Project = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        if project.version > PROJECT_VERSION {
+            return Err(ProjectError::UnsupportedVersion(project.version));
+        }
+        let base = project_dir(path);
+        for w in project.windows.iter_mut() {
+            if w.path.is_relative() {
+                w.path = base.join(&w.path);
+            }
+        }
+        Ok(project)
+    }
+}
+
+fn project_dir(path:&Path) -> PathBuf {
+    let path = std::path::absolute(path).unwrap_or(path.to_path_buf());
+    path.parent().map(|p| p.to_path_buf()).unwrap_or_default()
+}
+
+pub fn is_project_file(path:&Path) -> bool {
+    path.extension().is_some_and(|e| e.eq_ignore_ascii_case(PROJECT_EXTENSION))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgb;
+
+    fn temp_dir(name:&str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("iris_project_{}_{}",name,std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn window(path:PathBuf) -> ProjectWindow {
+        let mut color = iris_color::PaletteColor::from_rgb(Rgb([200,40,40]),[3,4]);
+        color.colors.push(iris_color::PaletteColor::from_rgb(Rgb([190,50,40]),[1,1]));
+        color.marked = true;
+        ProjectWindow{
+            path,
+            avaraging_system:AvarageingSystem::MedianCuttin,
+            color_gradation:0.2,
+            color_dist_type:iris_color::ColorSpace::CieLab,
+            color_display_threshhold:0.01,
+            clean_up_value:1.5,
+            median_cut_amount:7,
+            mean_schift_radius:12.0,
+            compare_state:CompareState::Saturation,
+            mark_every_color:true,
+            current_frame:2,
+            scan_all_frames:true,
+            tone_mapper:iris_hdr::ToneMapper::Reinhard,
+            exposure:-1.0,
+            scan_scene_linear:true,
+            median_method:iris_scan::MedianMethod::Medoid,
+            selection:Some(iris_selection::Selection::Polygon{points:vec![[0.0,0.0],[5.0,0.5],[2.0,4.0]]}),
+            background:iris_background::BackgroundSettings{mode:iris_background::BackgroundMode::FloodFill,tolerance:0.1},
+            filter:iris_filter::ColorFilter{ranges:vec![iris_filter::RangeFilter::default()],excluded:vec![]},
+            pixel_totals:iris_scan::PixelTotals{total:40,transparent:4,excluded:2,background:10,filtered:12},
+            color_list:HashMap::from([(0,color)]),
+            color_percent:HashMap::from([(0,1.0)]),
+            color_pixel_count:HashMap::from([(0,12)]),
+        }
+    }
+
+    #[test]
+    fn save_and_load() {
+        let dir = temp_dir("round_trip");
+        let inside = dir.join("images").join("cat.png");
+        let outside = std::env::temp_dir().join("elsewhere").join("dog.png");
+        let file = dir.join("session.iris");
+        let global = vec![iris_color::PaletteColor::from_rgb(Rgb([1,2,3]),[0,0])];
+        Project::new(vec![window(inside.clone()),window(outside.clone())],global).save(&file).unwrap();
+
+        // images in the project folder are stored relative to it
+        let json:serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&file).unwrap()).unwrap();
+        assert_eq!(Path::new(json["windows"][0]["path"].as_str().unwrap()),Path::new("images").join("cat.png"));
+        assert_eq!(Path::new(json["windows"][1]["path"].as_str().unwrap()),outside);
+
+        let project = Project::load(&file).unwrap();
+        assert_eq!(project.version,PROJECT_VERSION);
+        assert_eq!(project.windows[0].path,inside);
+        assert_eq!(project.windows[1].path,outside);
+        assert_eq!(project.global_colors[0].to_rgb(),Rgb([1,2,3]));
+        let (loaded,expected) = (&project.windows[0],window(inside));
+        assert_eq!(loaded.avaraging_system,expected.avaraging_system);
+        assert_eq!(loaded.median_cut_amount,7);
+        assert_eq!(loaded.compare_state,CompareState::Saturation);
+        assert_eq!(loaded.tone_mapper,expected.tone_mapper);
+        assert_eq!(loaded.median_method,expected.median_method);
+        assert_eq!(loaded.selection,expected.selection);
+        assert_eq!(loaded.background,expected.background);
+        assert_eq!(loaded.filter,expected.filter);
+        assert_eq!(loaded.pixel_totals,expected.pixel_totals);
+        let color = &loaded.color_list[&0];
+        assert_eq!((color.to_rgb(),color.position,color.marked,color.colors.len()),(Rgb([200,40,40]),[3,4],true,1));
+        assert_eq!(loaded.color_pixel_count[&0],12);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn load_old_project() {
+        // written before frames, hdr, selections, background and filters were saved
+        let old = r#"{
+            "version": 1,
+            "windows": [{
+                "path": "cat.png",
+                "avaraging_system": "DeltaE",
+                "color_gradation": 0.1,
+                "color_dist_type": "OkLab",
+                "color_display_threshhold": 0.0,
+                "clean_up_value": 0.0,
+                "median_cut_amount": 4,
+                "mean_schift_radius": 10.0,
+                "compare_state": "Percentages",
+                "mark_every_color": false,
+                "color_list": {"0": {"r": 10, "g": 20, "b": 30, "color_n": 1}},
+                "color_percent": {"0": 1.0},
+                "color_pixel_count": {"0": 5}
+            }],
+            "global_colors": []
+        }"#;
+        let dir = temp_dir("old");
+        let file = dir.join("old.iris");
+        std::fs::write(&file,old).unwrap();
+        let project = Project::load(&file).unwrap();
+        let w = &project.windows[0];
+        assert_eq!(w.path,dir.join("cat.png"));
+        assert_eq!((w.current_frame,w.scan_all_frames,w.scan_scene_linear),(0,false,false));
+        assert_eq!(w.tone_mapper,iris_hdr::ToneMapper::default());
+        assert_eq!(w.median_method,iris_scan::MedianMethod::PerChannel);
+        assert_eq!(w.selection,None);
+        assert_eq!(w.background,iris_background::BackgroundSettings::default());
+        assert!(!w.filter.is_active());
+        assert_eq!(w.pixel_totals,iris_scan::PixelTotals::default());
+        let color = &w.color_list[&0];
+        assert_eq!(color.to_rgb(),Rgb([10,20,30]));
+        assert!(color.colors.is_empty() && color.spatial.is_none());
+
+        std::fs::write(&file,old.replace("\"version\": 1","\"version\": 99")).unwrap();
+        assert!(matches!(Project::load(&file),Err(ProjectError::UnsupportedVersion(99))));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
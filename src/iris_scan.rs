@@ -0,0 +1,996 @@
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use image::{DynamicImage, GenericImageView, Pixel, Rgb};
+use itertools::Itertools;
+
+use super::iris_color;
+use serde::{Deserialize, Serialize};
+
+use super::AvarageingSystem;
+
+// how the single color of a median color scan is found
+#[derive(Debug,PartialEq,Clone,Copy,Default,Serialize,Deserialize)]
+pub enum MedianMethod {
+    // median of every channel on its own, the result may exist nowhere in the image
+    #[default]
+    PerChannel,
+    // the color with the smallest summed OkLab distance to all pixels (Weiszfeld)
+    GeometricMedian,
+    // the pixel color with the smallest summed OkLab distance to all pixels
+    Medoid,
+}
+
+impl std::fmt::Display for MedianMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::PerChannel => write!(f,"Per channel median"),
+            Self::GeometricMedian => write!(f,"Geometric median"),
+            Self::Medoid => write!(f,"Medoid"),
+        }
+    }
+}
+
+#[derive(Debug,PartialEq,Clone,Copy)]
+pub struct ScanSettings {
+    pub avaraging_system:AvarageingSystem,
+    pub color_gradation:f32,
+    pub color_dist_type:iris_color::ColorSpace,
+    pub clean_up_value:f32,
+    pub median_cut_amount:u32,
+    pub mean_schift_radius:f32,
+    pub median_method:MedianMethod,
+}
+
+pub const NO_LABEL:u32 = u32::MAX;
+
+// upper end of the clean up threshold, it is measured in the scan's color space
+pub fn clean_up_max(color_dist_type:iris_color::ColorSpace) -> f32 {
+    match color_dist_type {
+        iris_color::ColorSpace::OkLab => 0.1,
+        iris_color::ColorSpace::CieLab => 10.0,
+        iris_color::ColorSpace::Rgb => 25.0,
+    }
+}
+
+// pixels of the scanned image that went into clusters, and the ones that were left out
+#[derive(Debug,PartialEq,Clone,Copy,Default,Serialize,Deserialize)]
+pub struct PixelTotals {
+    pub total:u32,
+    pub transparent:u32,
+    // opaque pixels that belong to no cluster
+    pub excluded:u32,
+    // opaque pixels left out as background
+    #[serde(default)]
+    pub background:u32,
+    // opaque pixels left out by the color filters
+    #[serde(default)]
+    pub filtered:u32,
+}
+
+impl PixelTotals {
+    pub fn scanned(&self) -> u32 {
+        self.total - self.transparent - self.excluded - self.background - self.filtered
+    }
+}
+
+// why a pixel is left out of a scan, one per pixel row by row
+#[derive(Debug,PartialEq,Clone,Copy,Default)]
+pub enum Exclusion {
+    #[default]
+    None,
+    // outside the selected region
+    Region,
+    Background,
+    // by the color filters
+    Filtered,
+}
+
+// every algorithm returns the same: color_pixel_count is the number of pixels labeled with the color,
+// color_percent is that count divided by the scanned pixels (opaque and not excluded), so the percentages add up to 1.
+// colors without a pixel are dropped
+#[derive(Clone,Default)]
+pub struct ColorScan {
+    pub color_list:HashMap<u32,iris_color::PaletteColor>,
+    pub color_percent:HashMap<u32,f32>,
+    pub color_pixel_count:HashMap<u32,u32>,
+    // color_list key of every pixel, row by row. NO_LABEL for transparent pixels
+    pub labels:Vec<u32>,
+    pub totals:PixelTotals,
+}
+
+#[derive(Clone)]
+struct MedianCut {
+    median_color:[u8;3],
+    colors:Vec<([u8;3],[u32;2])>,
+    position:[u32;2],
+}
+
+// centroid, bounding box and spread of every label. rows below frame_height continue at the top,
+// so frames stacked for a combined scan all land on one frame
+pub fn spatial_stats(labels:&[u32],width:u32,frame_height:u32) -> HashMap<u32,iris_color::SpatialStats> {
+    // count, sum of x and y, sum of x² and y², bounding box
+    type Sums = (f64,[f64;2],[f64;2],[u32;4]);
+    let mut sums:HashMap<u32,Sums> = HashMap::new();
+    for (i,label) in labels.iter().enumerate() {
+        if *label == NO_LABEL {
+            continue;
+        }
+        let x = i as u32 % width.max(1);
+        let y = (i as u32 / width.max(1)) % frame_height.max(1);
+        let entry = sums.entry(*label).or_insert((0.0,[0.0;2],[0.0;2],[x,y,x,y]));
+        entry.0 += 1.0;
+        entry.1[0] += x as f64;
+        entry.1[1] += y as f64;
+        entry.2[0] += (x as f64).powi(2);
+        entry.2[1] += (y as f64).powi(2);
+        entry.3 = [entry.3[0].min(x),entry.3[1].min(y),entry.3[2].max(x),entry.3[3].max(y)];
+    }
+    sums.into_iter().map(|(label,(n,sum,sum_squared,bounding_box))| {
+        let centroid = [sum[0] / n,sum[1] / n];
+        let spread = [0,1].map(|i| (sum_squared[i] / n - centroid[i].powi(2)).max(0.0).sqrt() as f32);
+        (label,iris_color::SpatialStats{centroid:centroid.map(|c| c as f32),bounding_box,spread})
+    }).collect()
+}
+
+// the centroid becomes the position of the color
+pub fn apply_spatial_stats(color_list:&mut HashMap<u32,iris_color::PaletteColor>,stats:&HashMap<u32,iris_color::SpatialStats>){
+    for (id,color) in color_list.iter_mut() {
+        color.spatial = stats.get(id).copied();
+        if let Some(spatial) = &color.spatial {
+            color.position = spatial.centroid.map(|c| c.round() as u32);
+        }
+    }
+}
+
+// geometric median or medoid of the pixel colors in OkLab
+fn central_color(pixels:&[(Rgb<u8>,[u32;2])],median_method:MedianMethod) -> Rgb<u8> {
+    // the medoid is searched among this many colors closest to the geometric median once there are too many to try them all
+    const MEDOID_CANDIDATES:usize = 256;
+    let mut weights:HashMap<[u8;3],f64> = HashMap::new();
+    for (rgb,_) in pixels {
+        *weights.entry(rgb.0).or_insert(0.0) += 1.0;
+    }
+    let colors:Vec<([u8;3],[f32;3],f64)> = weights.into_iter().map(|(rgb,w)| {
+        let lab = iris_color::OkLab::from_rgb(&Rgb(rgb));
+        (rgb,[lab.l,lab.a,lab.b],w)
+    }).collect();
+    let distance = |a:[f32;3],b:[f32;3]| (((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)) as f64).sqrt();
+
+    // Weiszfeld, starting at the mean
+    let total:f64 = colors.iter().map(|c| c.2).sum();
+    let mut median = [0,1,2].map(|i| (colors.iter().map(|c| c.1[i] as f64 * c.2).sum::<f64>() / total) as f32);
+    for _ in 0..100 {
+        let mut sum = [0.0_f64;3];
+        let mut weight_sum = 0.0;
+        for (_,lab,w) in colors.iter() {
+            let d = distance(*lab,median);
+            // a point sitting on the estimate would divide by zero
+            if d < 1e-9 {
+                continue;
+            }
+            for i in 0..3 {
+                sum[i] += lab[i] as f64 * w / d;
+            }
+            weight_sum += w / d;
+        }
+        if weight_sum == 0.0 {
+            break;
+        }
+        let next = sum.map(|s| (s / weight_sum) as f32);
+        let moved = distance(next,median);
+        median = next;
+        if moved < 1e-7 {
+            break;
+        }
+    }
+    if median_method == MedianMethod::GeometricMedian {
+        return Rgb(iris_color::OkLab::new(median[0],median[1],median[2]).to_rgb());
+    }
+    let mut candidates:Vec<&([u8;3],[f32;3],f64)> = colors.iter().collect();
+    if candidates.len() > MEDOID_CANDIDATES {
+        candidates.sort_by(|a,b| distance(a.1,median).total_cmp(&distance(b.1,median)));
+        candidates.truncate(MEDOID_CANDIDATES);
+    }
+    let cost = |candidate:&[f32;3]| colors.iter().map(|(_,lab,w)| distance(*lab,*candidate) * w).sum::<f64>();
+    candidates.iter().map(|c| (c.0,cost(&c.1))).min_by(|a,b| a.1.total_cmp(&b.1)).map(|(rgb,_)| Rgb(rgb)).unwrap_or(pixels[0].0)
+}
+
+// excluded pixels are made transparent, so every algorithm skips them
+pub fn mask_image(image:&DynamicImage,exclusions:&[Exclusion]) -> DynamicImage {
+    let mut region = image.to_rgba8();
+    for (pixel,exclusion) in region.pixels_mut().zip(exclusions.iter().chain(std::iter::repeat(&Exclusion::Region))) {
+        if *exclusion != Exclusion::None {
+            pixel.0[3] = 0;
+        }
+    }
+    DynamicImage::ImageRgba8(region)
+}
+
+// scans only the pixels that aren't excluded, they are counted by the reason they were left out
+pub fn scan_region(image:&DynamicImage,settings:&ScanSettings,exclusions:Option<&[Exclusion]>) -> ColorScan {
+    let Some(exclusions) = exclusions else {
+        return scan(image,settings);
+    };
+    let mut scan = ColorScan::default();
+    scan.run(&mask_image(image,exclusions),settings);
+    scan.count_pixels(image,exclusions);
+    scan
+}
+
+pub fn scan(image:&DynamicImage,settings:&ScanSettings) -> ColorScan {
+    let mut scan = ColorScan::default();
+    scan.run(image,settings);
+    scan.count_pixels(image,&[]);
+    scan
+}
+
+impl ColorScan {
+    fn run(&mut self,image:&DynamicImage,settings:&ScanSettings){
+        match settings.avaraging_system {
+            AvarageingSystem::DeltaE => self.delta_e(image,settings),
+            AvarageingSystem::MedianColor => self.median_color(image,settings.median_method),
+            AvarageingSystem::MedianCuttin => self.median_cutting(image,settings.median_cut_amount),
+            AvarageingSystem::MeanShift => self.mean_shift(image,settings.mean_schift_radius),
+        }
+    }
+    // counts and percentages are taken from the labels, so they mean the same for every algorithm
+    fn count_pixels(&mut self,image:&DynamicImage,exclusions:&[Exclusion]){
+        let mut totals = PixelTotals{total:self.labels.len() as u32,..Default::default()};
+        let mut counts:HashMap<u32,u32> = HashMap::new();
+        for (i,((_,_,rgba),label)) in image.pixels().zip(self.labels.iter_mut()).enumerate() {
+            if rgba[3] == 0 {
+                totals.transparent += 1;
+                *label = NO_LABEL;
+            }else if exclusions.get(i) == Some(&Exclusion::Background) {
+                totals.background += 1;
+                *label = NO_LABEL;
+            }else if exclusions.get(i) == Some(&Exclusion::Filtered) {
+                totals.filtered += 1;
+                *label = NO_LABEL;
+            }else if self.color_list.contains_key(label) {
+                *counts.entry(*label).or_insert(0) += 1;
+            }else{
+                totals.excluded += 1;
+                *label = NO_LABEL;
+            }
+        }
+        self.color_list.retain(|id,_| counts.contains_key(id));
+        let scanned = totals.scanned().max(1) as f64;
+        self.color_percent = counts.iter().map(|(id,count)| (*id,(*count as f64 / scanned) as f32)).collect();
+        self.color_pixel_count = counts;
+        self.totals = totals;
+        apply_spatial_stats(&mut self.color_list,&spatial_stats(&self.labels,image.width(),image.height()));
+    }
+    fn label_by_color(&mut self,image:&DynamicImage,lookup:&HashMap<[u8;3],u32>){
+        self.labels = image.pixels().map(|(_,_,rgba)| {
+            if rgba[3] == 0 {NO_LABEL} else {lookup.get(&rgba.to_rgb().0).copied().unwrap_or(NO_LABEL)}
+        }).collect();
+    }
+    fn median_color(&mut self,image:&DynamicImage,median_method:MedianMethod){
+
+        self.color_percent = HashMap::new();
+        self.color_list = HashMap::new();
+
+        let mut color_vec:Vec<(Rgb<u8>,[u32;2])> = vec![];
+
+        for (x,y,rgba) in image.pixels(){
+            if !(rgba.channels()[3]<= 0){
+                let rgb = rgba.to_rgb();
+                color_vec.push((rgb,[x,y]));
+            }
+        }
+        if color_vec.is_empty() {
+            self.labels = vec![NO_LABEL;(image.width() * image.height()) as usize];
+            return;
+        }
+        if median_method != MedianMethod::PerChannel {
+            // the position is replaced by the centroid of the pixels afterwards
+            let rgb = central_color(&color_vec,median_method);
+            self.color_list.insert(0,iris_color::PaletteColor::from_rgb(rgb,[0,0]));
+            self.labels = image.pixels().map(|(_,_,rgba)| if rgba[3] == 0 {NO_LABEL} else {0}).collect();
+            return;
+        }
+         
+        color_vec.sort_by(|a,b| a.0.0[0].partial_cmp(&b.0.0[0]).unwrap());
+        let r:u8; 
+        let r_pos:[u32;2];
+        if color_vec.len() % 2 == 0 {
+            let upper = color_vec[color_vec.len()/2].0.0[0];
+            let lower = color_vec[(color_vec.len()/2)-1].0.0[0];
+
+            let upper_pos = color_vec[color_vec.len()/2].1;
+            let lower_pos = color_vec[color_vec.len()/2-1].1;
+
+            r = ((upper as u32 + lower as u32)/2).min(255) as u8;
+            r_pos = [(upper_pos[0] + lower_pos[0])/2,(upper_pos[1] + lower_pos[1])/2];
+        }else{
+            r = color_vec[color_vec.len()/2].0.0[0];
+            r_pos = color_vec[color_vec.len()/2].1;
+        }
+        color_vec.sort_by(|a,b| a.0.0[1].partial_cmp(&b.0.0[1]).unwrap());
+        let g:u8; 
+        let g_pos:[u32;2];
+        if color_vec.len() % 2 == 0 {
+            let upper = color_vec[color_vec.len()/2].0.0[1];
+            let lower = color_vec[(color_vec.len()/2)-1].0.0[1];
+
+            let upper_pos = color_vec[color_vec.len()/2].1;
+            let lower_pos = color_vec[color_vec.len()/2-1].1;
+
+            g = ((upper as u32 + lower as u32)/2).min(255) as u8;
+            g_pos = [(upper_pos[0] + lower_pos[0])/2,(upper_pos[1] + lower_pos[1])/2];
+        }else{
+            g = color_vec[color_vec.len()/2].0.0[1];
+            g_pos = color_vec[color_vec.len()/2].1;
+        }
+        color_vec.sort_by(|a,b| a.0.0[2].partial_cmp(&b.0.0[2]).unwrap());
+        let b:u8; 
+        let b_pos:[u32;2];
+        if color_vec.len() % 2 == 0 {
+            let upper = color_vec[color_vec.len()/2].0.0[2];
+            let lower = color_vec[(color_vec.len()/2)-1].0.0[2];
+
+            let upper_pos = color_vec[color_vec.len()/2].1;
+            let lower_pos = color_vec[color_vec.len()/2-1].1;
+
+            b = ((upper as u32 + lower as u32)/2).min(255) as u8;
+            b_pos = [(upper_pos[0] + lower_pos[0])/2,(upper_pos[1] + lower_pos[1])/2];
+        }else{
+            b = color_vec[color_vec.len()/2].0.0[2];
+            b_pos = color_vec[color_vec.len()/2].1;
+        }
+
+        let median_color:Rgb<u8> = Rgb::from([r,g,b]);
+        let median_pos:[u32;2] = [(r_pos[0]+g_pos[0]+b_pos[0])/3,(r_pos[1]+g_pos[1]+b_pos[1])/3];
+        let avarage_median = iris_color::PaletteColor::from_rgb(median_color,median_pos);
+
+        self.color_list.insert(0,avarage_median);
+        self.labels = image.pixels().map(|(_,_,rgba)| if rgba[3] == 0 {NO_LABEL} else {0}).collect();
+
+
+    }
+    fn get_median_color(&self,colors:&mut Vec<([u8;3],[u32;2])>) -> ([u8;3],[u32;2]) {
+        colors.sort_by(|a,b| a.0[0].partial_cmp(&b.0[0]).unwrap());
+        let r:u8; 
+        let r_pos:[u32;2];
+        if colors.len() == 1 {
+            return colors[0];
+        }
+        if colors.len() % 2 == 0 {
+            let upper = colors[colors.len()/2].0[0];
+            let lower = colors[(colors.len()/2)-1].0[0];
+
+            let upper_pos = colors[colors.len()/2].1;
+            let lower_pos = colors[colors.len()/2-1].1;
+
+            r = ((upper as u32 + lower as u32)/2).min(255) as u8;
+            r_pos = [(upper_pos[0] + lower_pos[0])/2,(upper_pos[1] + lower_pos[1])/2];
+        }else{
+            r = colors[colors.len()/2].0[0];
+            r_pos = colors[colors.len()/2].1;
+        }
+        colors.sort_by(|a,b| a.0[1].partial_cmp(&b.0[1]).unwrap());
+        let g:u8; 
+        let g_pos:[u32;2];
+        if colors.len() % 2 == 0 {
+            let upper = colors[colors.len()/2].0[1];
+            let lower = colors[(colors.len()/2)-1].0[1];
+
+            let upper_pos = colors[colors.len()/2].1;
+            let lower_pos = colors[colors.len()/2-1].1;
+
+            g = ((upper as u32 + lower as u32)/2).min(255) as u8;
+            g_pos = [(upper_pos[0] + lower_pos[0])/2,(upper_pos[1] + lower_pos[1])/2];
+        }else{
+            g = colors[colors.len()/2].0[1];
+            g_pos = colors[colors.len()/2].1;
+        }
+        colors.sort_by(|a,b| a.0[2].partial_cmp(&b.0[2]).unwrap());
+        let b:u8; 
+        let b_pos:[u32;2];
+        if colors.len() % 2 == 0 {
+            let upper = colors[colors.len()/2].0[2];
+            let lower = colors[(colors.len()/2)-1].0[2];
+
+            let upper_pos = colors[colors.len()/2].1;
+            let lower_pos = colors[colors.len()/2-1].1;
+
+            b = ((upper as u32 + lower as u32)/2).min(255) as u8;
+            b_pos = [(upper_pos[0] + lower_pos[0])/2,(upper_pos[1] + lower_pos[1])/2];
+        }else{
+            b = colors[colors.len()/2].0[2];
+            b_pos = colors[colors.len()/2].1;
+        }
+
+        let median_pos:[u32;2] = [(r_pos[0]+g_pos[0]+b_pos[0])/3,(r_pos[1]+g_pos[1]+b_pos[1])/3];
+
+        ([r,g,b],median_pos)
+    }
+
+
+    fn mean_shift(&mut self,image:&DynamicImage,mean_schift_radius:f32) {
+       let true_radius = mean_schift_radius/100.0; 
+
+        self.color_percent = HashMap::new();
+        self.color_list = HashMap::new();
+        self.color_pixel_count = HashMap::new();
+        let _size = image.width() as f64 * image.height() as f64;
+
+        let mut color_rgb_values:HashSet<[u8;3]>= HashSet::new();
+
+        let mut end_points:Vec<MeanShiftCursor> = vec![];
+
+        for (_x,_y,rgba) in image.pixels(){
+            if !(rgba.channels()[3]<= 0){
+                let rgb = rgba.to_rgb();
+                if !color_rgb_values.contains(&rgb.0) {
+                    color_rgb_values.insert(rgb.0); 
+                }
+            }
+        }
+        for c in color_rgb_values.iter() {
+            // Rgb Value of Lab Position of ?Target/cursor
+            let mut current_pos = MeanShiftCursor::new(*c,true_radius);
+    
+            let mut looping = true;
+            while looping{
+                let mut added_new_color = false;
+                //current cursor pos as lab 
+                for secondary_color in color_rgb_values.iter() {
+                    if current_pos.inside_radius(*secondary_color){
+                        let added_color = current_pos.add_color(*secondary_color);
+                        if added_new_color == false {
+                            added_new_color = added_color;
+                        }
+                    }
+                }
+                if added_new_color {
+                    current_pos.move_to_color_avarage();
+                }else{
+                    // a cursor that ends up at a known end point hands its colors over, so its pixels keep a label
+                    match end_points.iter_mut().find(|c| c.is_same_as(&current_pos)) {
+                        Some(end_point) => end_point.colors.extend(current_pos.colors.iter().copied()),
+                        None => end_points.push(current_pos.clone()),
+                    }
+                    looping = false;
+                }
+            }
+            
+        }
+        let mut lookup:HashMap<[u8;3],u32> = HashMap::new();
+        for cursor in end_points {
+            let id = self.color_list.len() as u32;
+            for c in cursor.colors.iter() {
+                lookup.entry(*c).or_insert(id);
+            }
+            let mut av_color = iris_color::PaletteColor::from_rgb(Rgb::from(cursor.rgb_color),[0,0]);
+            for c in cursor.colors{
+                let sub_av_color = iris_color::PaletteColor::from_rgb(Rgb::from(c),[0,0]);
+                av_color.colors.push(sub_av_color);
+            }
+            self.color_list.insert(id, av_color);
+        }
+        self.label_by_color(image,&lookup);
+    }
+
+    fn median_cutting(&mut self,image:&DynamicImage,median_cut_amount:u32){
+        self.color_percent = HashMap::new();
+        self.color_list = HashMap::new();
+        self.color_pixel_count = HashMap::new();
+        let _size = image.width() as f64 * image.height() as f64;
+
+        let mut color_rgb_values:HashMap<[u8;3],[u32;2]>= HashMap::new();
+       
+
+        for (x,y,rgba) in image.pixels(){
+            if !(rgba.channels()[3]<= 0){
+                let rgb = rgba.to_rgb();
+                if !color_rgb_values.contains_key(&rgb.0) {
+                    color_rgb_values.insert(rgb.0,[x,y]); 
+                }
+            }
+        }
+        let mut color_vec = color_rgb_values.into_iter().collect_vec();
+        if color_vec.is_empty() {
+            self.labels = vec![NO_LABEL;(image.width() * image.height()) as usize];
+            return;
+        }
+        let result = self.get_median_color(&mut color_vec);
+        let mut cuts:Vec<MedianCut> = vec![MedianCut{median_color:result.0,colors:color_vec,position:result.1}];
+        for _ in 0..median_cut_amount {
+            // the biggest box can't be split any more, so neither can the others
+            if cuts.last().is_none_or(|c| c.colors.len() < 2) {
+                break;
+            }
+            let target = cuts.pop(); 
+            if let Some(mut t) = target {
+                let median_cut_pair = self.median_cut(&mut t.colors);
+                cuts.push(median_cut_pair[0].clone());
+                cuts.push(median_cut_pair[1].clone());
+            }
+            cuts.sort_by(|a,b| a.colors.len().partial_cmp(&b.colors.len()).unwrap());
+        }
+        let mut lookup:HashMap<[u8;3],u32> = HashMap::new();
+        for median_cut in cuts {
+            let key = self.color_list.len() as u32;
+            for c in median_cut.colors.iter() {
+                lookup.insert(c.0,key);
+            }
+            let mut avarage_median = iris_color::PaletteColor::from_rgb(Rgb::from(median_cut.median_color),median_cut.position);
+            for c in median_cut.colors.clone().into_iter() {
+                if c.0 == median_cut.median_color {
+                    break;
+                }
+                let ac_buffer = iris_color::PaletteColor::from_rgb(Rgb::from(c.0),c.1);
+                avarage_median.colors.push(ac_buffer);
+            }
+            self.color_list.insert(key,avarage_median);
+        }
+        self.label_by_color(image,&lookup);
+
+    }
+
+
+    fn median_cut(&self,colors:&mut Vec<([u8;3],[u32;2])>) -> [MedianCut;2] {
+        // range = [max,min]
+        let mut r_range:[u8;2] = [0,u8::MAX];
+        let mut g_range:[u8;2] = [0,u8::MAX];
+        let mut b_range:[u8;2] = [0,u8::MAX];
+
+        for c in colors.iter() {
+            r_range[0] = r_range[0].max(c.0[0]);
+            r_range[1] = r_range[1].min(c.0[0]);
+
+            g_range[0] = g_range[0].max(c.0[1]);
+            g_range[1] = g_range[1].min(c.0[1]);
+
+            b_range[0] = b_range[0].max(c.0[2]);
+            b_range[1] = b_range[1].min(c.0[2]);
+        }
+        let biggest_range:usize;
+
+        let r_range_num = r_range[0] - r_range[1];
+        let g_range_num = g_range[0] - g_range[1];
+        let b_range_num = b_range[0] - b_range[1];
+
+        if  r_range_num > g_range_num && r_range_num > b_range_num {
+            biggest_range = 0;
+        }else if g_range_num > r_range_num && g_range_num > b_range_num {
+            biggest_range = 1;
+        }else {
+            biggest_range = 2;
+        }
+        if !biggest_range < 3 {
+            panic!();
+        }
+        colors.sort_by(|a,b| a.0[biggest_range].partial_cmp(&b.0[biggest_range]).unwrap());
+        let median = colors.len()/2;
+        let mut top_slice = colors[0..median].to_vec();
+        let mut bot_slice = colors[median..colors.len()].to_vec();
+        let top_color = self.get_median_color(&mut top_slice);
+        let bot_color = self.get_median_color(&mut bot_slice);
+        [MedianCut{colors:top_slice,median_color:top_color.0,position:top_color.1},MedianCut{colors:bot_slice,median_color:bot_color.0,position:bot_color.1}]
+    }
+
+    fn delta_e(&mut self,image:&DynamicImage,settings:&ScanSettings){
+        let size = image.width() as f64 * image.height() as f64;
+        self.color_percent = HashMap::new();
+        self.color_list = HashMap::new();
+        self.color_pixel_count = HashMap::new();
+        let mut max_dist = f32::MIN;
+        let mut min_dist = f32::MAX;
+        let mut transparent_pixels:f64 = 0.0;
+        let width = image.width();
+        self.labels = vec![NO_LABEL;size as usize];
+        for (x,y,rgba) in image.pixels(){
+            if !(rgba.channels()[3]<= 0){
+                let rgb = rgba.to_rgb();
+                let mut rgb_already_registered = false;
+                let mut closest_color_dist:f32 = f32::MAX;
+                let mut closest_color_key:Option<u32> = None;
+                if settings.color_gradation >= 0.0 {
+                    for (key,value) in self.color_list.iter_mut(){
+                        let dist:f32;
+                        match settings.color_dist_type{
+                            iris_color::ColorSpace::Rgb => dist = iris_color::rgb_distance(value.to_rgb(), rgb),
+                            iris_color::ColorSpace::CieLab => dist = {
+                                let lab_a = iris_color::CieLab::from_rgb(value.to_rgb());
+                                let lab_b = iris_color::CieLab::from_rgb(rgb);
+                                lab_a.distance_to_lab(&lab_b)
+                            },
+                            iris_color::ColorSpace::OkLab => dist = {
+                                let lab_a = iris_color::OkLab::from_rgb(&value.to_rgb());
+                                let lab_b = iris_color::OkLab::from_rgb(&rgb);
+                                // max_dist = max_dist.max(lab_a.b);
+                                // min_dist = min_dist.min(lab_a.b);
+                                lab_a.distance_to_lab(&lab_b)
+                            },
+                        }
+                        max_dist = max_dist.max(dist);
+                        min_dist = min_dist.min(dist);
+                        if dist <= settings.color_gradation{
+                            if closest_color_dist > dist {
+                                closest_color_dist = dist;
+                                closest_color_key = Some(*key);
+                            }
+                            rgb_already_registered = true;
+                        }
+                    }
+                }
+                if !rgb_already_registered {
+                    self.labels[(y * width + x) as usize] = self.color_list.len() as u32;
+                    self.color_percent.insert(self.color_list.len() as u32,(1.0/size)as f32);
+                    self.color_pixel_count.insert(self.color_list.len() as u32, 1);
+                    self.color_list.insert(self.color_list.len() as u32,iris_color::PaletteColor::from_rgb(rgb,[x,y]));
+                }else if let Some(cck) = closest_color_key{
+                    self.labels[(y * width + x) as usize] = cck;
+                    if let Some(value) = self.color_list.get_mut(&cck){
+                        if settings.color_gradation > 0.0 {
+                            value.avarage_with_rgb(&rgb,[x,y]);
+                        }
+                        if let Some(percent) = self.color_percent.get_mut(&cck){
+                            *percent += (1.0/size) as f32;
+                        }
+                        if let Some(count) = self.color_pixel_count.get_mut(&cck){
+                            *count += 1;
+                        }
+                    }
+                }
+            }else{
+                transparent_pixels += 1.0;
+            }
+        }
+        // println!("{} >> {}",max_dist,min_dist);
+        for (_,p) in self.color_percent.iter_mut(){
+            *p = ((*p as f64 *size)/(size-transparent_pixels)) as f32;
+        }
+
+        self.clean_up(settings.clean_up_value,settings.color_dist_type);
+    }
+    // merges every group of colors closer than clean_up_value to each other, repeated until no two colors are that close.
+    // the merged color is the pixel weighted mean in linear light and keeps the colors and sub colors of all members
+    fn clean_up(&mut self,clean_up_value:f32,color_dist_type:iris_color::ColorSpace) {
+        let mut merged_into:HashMap<u32,u32> = HashMap::new();
+        loop {
+            let ids:Vec<u32> = self.color_list.keys().copied().sorted().collect();
+            let mut parent:Vec<usize> = (0..ids.len()).collect();
+            fn find(parent:&mut [usize],i:usize) -> usize {
+                let mut root = i;
+                while parent[root] != root {
+                    root = parent[root];
+                }
+                let mut i = i;
+                while parent[i] != root {
+                    let next = parent[i];
+                    parent[i] = root;
+                    i = next;
+                }
+                root
+            }
+            let mut merged = false;
+            for (a,b) in (0..ids.len()).tuple_combinations() {
+                let dist = iris_color::color_distance(self.color_list[&ids[a]].to_rgb(),self.color_list[&ids[b]].to_rgb(),color_dist_type);
+                if dist <= clean_up_value {
+                    let (root_a,root_b) = (find(&mut parent,a),find(&mut parent,b));
+                    if root_a != root_b {
+                        parent[root_b] = root_a;
+                        merged = true;
+                    }
+                }
+            }
+            if !merged {
+                break;
+            }
+            let mut groups:HashMap<usize,Vec<u32>> = HashMap::new();
+            for (i,id) in ids.iter().enumerate() {
+                let root = find(&mut parent,i);
+                groups.entry(root).or_default().push(*id);
+            }
+            for members in groups.into_values().filter(|m| m.len() > 1) {
+                self.merge_colors(&members,&mut merged_into);
+            }
+        }
+        if !merged_into.is_empty() {
+            for label in self.labels.iter_mut() {
+                // a color can be merged into one that is merged itself later on
+                while let Some(target) = merged_into.get(label) {
+                    *label = *target;
+                }
+            }
+        }
+    }
+    // the member with the most pixels keeps its id
+    fn merge_colors(&mut self,members:&[u32],merged_into:&mut HashMap<u32,u32>){
+        let weight = |scan:&Self,id:&u32| scan.color_pixel_count.get(id).copied().unwrap_or(0).max(1) as f64;
+        let Some(&target) = members.iter().max_by(|a,b| weight(self,a).total_cmp(&weight(self,b)).then(b.cmp(a))) else {
+            return;
+        };
+        let total:f64 = members.iter().map(|id| weight(self,id)).sum();
+        let mut linear = [0.0_f64;3];
+        let mut position = [0.0_f64;2];
+        for id in members {
+            let w = weight(self,id) / total;
+            let c = &self.color_list[id];
+            for (sum,channel) in linear.iter_mut().zip([c.r,c.g,c.b]) {
+                *sum += iris_color::gamma_expand(channel as f64 / 255.0) * w;
+            }
+            position[0] += c.position[0] as f64 * w;
+            position[1] += c.position[1] as f64 * w;
+        }
+        let mut merged = self.color_list[&target].clone();
+        // the target is recolored below, so its own color becomes a sub color like the others
+        merged.colors.push(iris_color::PaletteColor::from_rgb(merged.to_rgb(),merged.position));
+        for id in members.iter().filter(|id| **id != target) {
+            let Some(other) = self.color_list.remove(id) else {
+                continue;
+            };
+            merged.color_n += other.color_n;
+            merged.colors.push(iris_color::PaletteColor::from_rgb(other.to_rgb(),other.position));
+            merged.colors.extend(other.colors);
+            if let Some(percent) = self.color_percent.remove(id) {
+                *self.color_percent.entry(target).or_insert(0.0) += percent;
+            }
+            if let Some(count) = self.color_pixel_count.remove(id) {
+                let value = self.color_pixel_count.entry(target).or_insert(0);
+                *value = value.checked_add(count).unwrap_or(u32::MAX);
+            }
+            merged_into.insert(*id,target);
+        }
+        let [r,g,b] = linear.map(|c| (iris_color::gamma_compress(c) * 255.0).round().clamp(0.0,255.0) as u8);
+        merged.r = r;
+        merged.g = g;
+        merged.b = b;
+        merged.position = position.map(|p| p.round() as u32);
+        self.color_list.insert(target,merged);
+    }
+}
+
+#[derive(Clone)]
+struct MeanShiftCursor{
+    pub leeway:f32,
+    lab_pos:iris_color::OkLab,
+    rgb_color:[u8;3],
+    colors:HashSet<[u8;3]>,
+    radius:f32,
+}
+impl MeanShiftCursor {
+    pub fn new(color:[u8;3],radius:f32) -> Self{
+        let lab_pos = iris_color::OkLab::from_rgb(&Rgb::from(color));
+        MeanShiftCursor{
+            leeway:0.2,
+            lab_pos,
+            rgb_color:color,
+            colors:HashSet::new(),
+            radius,
+            
+        }
+    } 
+    pub fn update_rgb_color(&mut self){
+        self.rgb_color = self.lab_pos.to_rgb();
+    }
+    pub fn move_to_color_avarage(&mut self){
+        // the mean of the colors alone, the old position isn't part of it
+        self.lab_pos = iris_color::OkLab{l:0.0,a:0.0,b:0.0};
+        for c in self.colors.iter(){
+            let lab_b = iris_color::OkLab::from_rgb(&Rgb::from(*c));
+            self.lab_pos.add(&lab_b);
+        } 
+        self.lab_pos.diff(self.colors.len() as f32);
+        self.update_rgb_color();
+    }
+    pub fn add_color(&mut self,color:[u8;3]) -> bool{
+        if self.colors.contains(&color){
+            return false;
+        }
+        self.colors.insert(color);
+        true
+    }
+    pub fn inside_radius(&self,color:[u8;3]) -> bool {
+        let lab_b = iris_color::OkLab::from_rgb(&Rgb::from(color));
+        self.lab_pos.distance_to_lab(&lab_b) <= self.radius
+    }
+    pub fn is_same_as(&self,other:&Self) -> bool {
+        self.lab_pos.distance_to_lab(&other.lab_pos) <= (self.leeway + other.leeway)/2.0
+    }
+
+}
+impl Hash for MeanShiftCursor {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.rgb_color.hash(state);
+    } 
+}
+
+impl PartialEq for MeanShiftCursor {
+   fn eq(&self, other: &Self) -> bool {
+        self.lab_pos.distance_to_lab(&other.lab_pos) <= (self.leeway + other.leeway)/2.0
+   } 
+}
+impl Eq for MeanShiftCursor {
+    
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(avaraging_system:AvarageingSystem) -> ScanSettings {
+        ScanSettings{
+            avaraging_system,
+            color_gradation:0.1,
+            color_dist_type:iris_color::ColorSpace::OkLab,
+            clean_up_value:0.01,
+            median_cut_amount:3,
+            mean_schift_radius:10.0,
+            median_method:MedianMethod::PerChannel,
+        }
+    }
+
+    // four color blocks, the last column is transparent
+    fn test_image() -> DynamicImage {
+        let img = image::RgbaImage::from_fn(9,6,|x,y| {
+            match (x,y) {
+                (8,_) => image::Rgba([0,0,0,0]),
+                (0..4,0..3) => image::Rgba([230,20,20,255]),
+                (4..8,0..3) => image::Rgba([20,200,40,255]),
+                (_,3..5) => image::Rgba([30,40,220,255]),
+                _ => image::Rgba([240,240,240,255]),
+            }
+        });
+        DynamicImage::ImageRgba8(img)
+    }
+
+    #[test]
+    fn counts_follow_labels() {
+        let image = test_image();
+        for system in [AvarageingSystem::DeltaE,AvarageingSystem::MedianColor,AvarageingSystem::MedianCuttin,AvarageingSystem::MeanShift] {
+            let scan = scan(&image,&settings(system));
+            assert_eq!(scan.totals,PixelTotals{total:54,transparent:6,excluded:0,background:0,filtered:0},"{:?}",system);
+            assert_eq!(scan.labels.len(),54);
+            assert!(!scan.color_list.is_empty(),"{:?}",system);
+            for (id,count) in scan.color_pixel_count.iter() {
+                assert!(*count > 0,"{:?} color {} without pixels",system,id);
+                assert_eq!(*count as usize,scan.labels.iter().filter(|l| *l == id).count(),"{:?}",system);
+                assert!((scan.color_percent[id] - *count as f32 / 48.0).abs() < 1e-6,"{:?}",system);
+            }
+            assert_eq!(scan.color_pixel_count.values().sum::<u32>(),scan.totals.scanned(),"{:?}",system);
+            let percent:f32 = scan.color_percent.values().sum();
+            assert!((percent - 1.0).abs() < 1e-4,"{:?} percentages add up to {}",system,percent);
+            let mut ids:Vec<_> = scan.color_list.keys().collect();
+            ids.sort();
+            assert_eq!(ids,{let mut p:Vec<_> = scan.color_percent.keys().collect(); p.sort(); p},"{:?}",system);
+        }
+    }
+
+    #[test]
+    fn transparent_pixels_are_unlabeled() {
+        let image = test_image();
+        for system in [AvarageingSystem::DeltaE,AvarageingSystem::MedianColor,AvarageingSystem::MedianCuttin,AvarageingSystem::MeanShift] {
+            let scan = scan(&image,&settings(system));
+            for y in 0..6 {
+                assert_eq!(scan.labels[(y * 9 + 8) as usize],NO_LABEL,"{:?}",system);
+            }
+        }
+    }
+
+    #[test]
+    fn distinct_colors_get_their_share() {
+        let scan = scan(&test_image(),&settings(AvarageingSystem::DeltaE));
+        assert_eq!(scan.color_list.len(),4);
+        let mut shares:Vec<u32> = scan.color_pixel_count.values().copied().collect();
+        shares.sort();
+        assert_eq!(shares,vec![8,12,12,16]);
+    }
+
+    #[test]
+    fn cluster_centroids() {
+        let scan = scan(&test_image(),&settings(AvarageingSystem::DeltaE));
+        let red = scan.color_list.values().find(|c| c.r > 200 && c.g < 100).unwrap();
+        let spatial = red.spatial.unwrap();
+        assert_eq!(spatial.centroid,[1.5,1.0]);
+        assert_eq!(spatial.bounding_box,[0,0,3,2]);
+        assert!((spatial.spread[0] - 1.25_f32.sqrt()).abs() < 1e-5);
+        assert_eq!(red.position,[2,1]);
+    }
+
+    #[test]
+    fn stacked_frames_fold_onto_one() {
+        // the same 2x2 frame twice, label 0 in the top left corner of both
+        let labels = [0,1,1,1,0,1,1,1];
+        let stats = spatial_stats(&labels,2,2);
+        assert_eq!(stats[&0].centroid,[0.0,0.0]);
+        assert_eq!(stats[&0].bounding_box,[0,0,0,0]);
+        assert_eq!(stats[&1].bounding_box,[0,0,1,1]);
+    }
+
+    #[test]
+    fn region_scan_excludes_the_rest() {
+        let image = test_image();
+        // the red block only, the bottom row is background and the blue rows are filtered
+        let mask:Vec<Exclusion> = (0..54).map(|i| match (i % 9,i / 9) {
+            (0..4,0..3) => Exclusion::None,
+            (_,5) => Exclusion::Background,
+            (0..8,3..5) => Exclusion::Filtered,
+            _ => Exclusion::Region,
+        }).collect();
+        for system in [AvarageingSystem::DeltaE,AvarageingSystem::MedianColor,AvarageingSystem::MedianCuttin,AvarageingSystem::MeanShift] {
+            let scan = scan_region(&image,&settings(system),Some(&mask));
+            assert_eq!(scan.totals,PixelTotals{total:54,transparent:6,excluded:12,background:8,filtered:16},"{:?}",system);
+            assert_eq!(scan.color_list.len(),1,"{:?}",system);
+            let color = scan.color_list.values().next().unwrap().to_rgb();
+            assert_eq!(color.0,[230,20,20],"{:?}",system);
+        }
+    }
+
+    #[test]
+    fn chained_merge_keeps_every_color() {
+        // a and b are close, b and c are close, a and c are not
+        let (a,b,c) = ([200,40,40],[200,48,48],[200,56,56]);
+        let img = image::RgbaImage::from_fn(6,2,|x,_| {
+            let rgb = match x {0..2 => a,2..4 => b,_ => c};
+            image::Rgba([rgb[0],rgb[1],rgb[2],255])
+        });
+        let mut s = settings(AvarageingSystem::DeltaE);
+        s.color_gradation = 0.001;
+        s.clean_up_value = 0.0;
+        let mut scan = scan(&DynamicImage::ImageRgba8(img),&s);
+        assert_eq!(scan.color_list.len(),3);
+        let step = iris_color::color_distance(Rgb(a),Rgb(b),iris_color::ColorSpace::OkLab);
+        assert!(iris_color::color_distance(Rgb(a),Rgb(c),iris_color::ColorSpace::OkLab) > step * 1.2);
+        scan.clean_up(step * 1.2,iris_color::ColorSpace::OkLab);
+        assert_eq!(scan.color_list.len(),1);
+        let merged = scan.color_list.values().next().unwrap();
+        let subs:Vec<[u8;3]> = merged.colors.iter().map(|c| c.to_rgb().0).collect();
+        for rgb in [a,b,c] {
+            assert!(subs.contains(&rgb),"{:?} missing from {:?}",rgb,subs);
+        }
+        assert!(scan.labels.iter().all(|l| scan.color_list.contains_key(l)));
+    }
+
+    #[test]
+    fn mean_shift_labels_every_pixel() {
+        // further apart than the radius, close enough for their end points to be taken as one
+        let (a,b) = ([200,40,40],[160,60,50]);
+        let distance = iris_color::color_distance(Rgb(a),Rgb(b),iris_color::ColorSpace::OkLab);
+        assert!(distance > 0.05 && distance < 0.2,"{}",distance);
+        let img = image::RgbaImage::from_fn(4,4,|x,_| {
+            let rgb = if x < 2 {a} else {b};
+            image::Rgba([rgb[0],rgb[1],rgb[2],255])
+        });
+        let mut s = settings(AvarageingSystem::MeanShift);
+        s.mean_schift_radius = 5.0;
+        s.clean_up_value = 0.0;
+        let scan = scan(&DynamicImage::ImageRgba8(img),&s);
+        assert_eq!(scan.color_list.len(),1);
+        assert_eq!(scan.totals.excluded,0);
+        assert!(scan.labels.iter().all(|l| scan.color_list.contains_key(l)));
+        assert!((scan.color_percent.values().sum::<f32>() - 1.0).abs() < 1e-6);
+        assert_eq!(scan.color_pixel_count.values().sum::<u32>(),16);
+    }
+
+    #[test]
+    fn medoid_is_an_image_color() {
+        let image = test_image();
+        let colors = [[230,20,20],[20,200,40],[30,40,220],[240,240,240]];
+        for method in [MedianMethod::GeometricMedian,MedianMethod::Medoid] {
+            let mut settings = settings(AvarageingSystem::MedianColor);
+            settings.median_method = method;
+            let scan = scan(&image,&settings);
+            assert_eq!(scan.color_list.len(),1);
+            let color = scan.color_list.values().next().unwrap().to_rgb();
+            if method == MedianMethod::Medoid {
+                assert!(colors.contains(&color.0),"{:?}",color);
+            }
+        }
+    }
+
+    #[test]
+    fn fully_transparent_image() {
+        let image = DynamicImage::ImageRgba8(image::RgbaImage::new(4,4));
+        for system in [AvarageingSystem::DeltaE,AvarageingSystem::MedianColor,AvarageingSystem::MedianCuttin,AvarageingSystem::MeanShift] {
+            let scan = scan(&image,&settings(system));
+            assert!(scan.color_list.is_empty(),"{:?}",system);
+            assert!(scan.color_percent.is_empty(),"{:?}",system);
+            assert_eq!(scan.totals.scanned(),0);
+        }
+    }
+}
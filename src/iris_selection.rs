@@ -0,0 +1,180 @@
+use std::fmt;
+use egui::{Pos2, Rect};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug,PartialEq,Clone,Copy,Default)]
+pub enum SelectionTool {
+    #[default]
+    Rectangle,
+    Ellipse,
+    Lasso,
+    Polygon,
+}
+
+impl SelectionTool {
+    pub const ALL:[SelectionTool;4] = [Self::Rectangle,Self::Ellipse,Self::Lasso,Self::Polygon];
+
+    pub fn hint(&self) -> &'static str {
+        match self {
+            Self::Rectangle | Self::Ellipse => "Drag over the image",
+            Self::Lasso => "Drag around the region",
+            Self::Polygon => "Click the corners, double click to close",
+        }
+    }
+}
+
+impl fmt::Display for SelectionTool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Rectangle => write!(f,"Rectangle"),
+            Self::Ellipse => write!(f,"Ellipse"),
+            Self::Lasso => write!(f,"Lasso"),
+            Self::Polygon => write!(f,"Polygon"),
+        }
+    }
+}
+
+// part of the image scans are restricted to, in pixel coordinates
+#[derive(Debug,PartialEq,Clone,Serialize,Deserialize)]
+pub enum Selection {
+    // corners are inclusive
+    Rectangle{min:[u32;2],max:[u32;2]},
+    // the ellipse inside the rectangle
+    Ellipse{min:[u32;2],max:[u32;2]},
+    // pixel edges are whole numbers, the outline is closed from the last point to the first
+    Lasso{points:Vec<[f32;2]>},
+    Polygon{points:Vec<[f32;2]>},
+}
+
+impl Selection {
+    // rectangle or ellipse spanned by two corner pixels
+    pub fn from_corners(tool:SelectionTool,a:[u32;2],b:[u32;2]) -> Option<Self> {
+        let min = [a[0].min(b[0]),a[1].min(b[1])];
+        let max = [a[0].max(b[0]),a[1].max(b[1])];
+        match tool {
+            SelectionTool::Rectangle => Some(Self::Rectangle{min,max}),
+            SelectionTool::Ellipse => Some(Self::Ellipse{min,max}),
+            _ => None,
+        }
+    }
+
+    // pixels are inside when their center is
+    pub fn contains(&self,x:u32,y:u32) -> bool {
+        let (px,py) = (x as f32 + 0.5,y as f32 + 0.5);
+        match self {
+            Self::Rectangle{min,max} => x >= min[0] && x <= max[0] && y >= min[1] && y <= max[1],
+            Self::Ellipse{min,max} => {
+                let (rx,ry) = ((max[0] + 1 - min[0]) as f32 / 2.0,(max[1] + 1 - min[1]) as f32 / 2.0);
+                let (cx,cy) = (min[0] as f32 + rx,min[1] as f32 + ry);
+                ((px - cx) / rx).powi(2) + ((py - cy) / ry).powi(2) <= 1.0
+            }
+            Self::Lasso{points} | Self::Polygon{points} => crossings(points,py).iter().filter(|cx| **cx <= px).count() % 2 == 1,
+        }
+    }
+
+    // one entry per pixel, row by row
+    pub fn mask(&self,width:u32,height:u32) -> Vec<bool> {
+        let mut mask = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            match self {
+                // filled between pairs of edge crossings, testing every pixel against every edge is too slow for long lassos
+                Self::Lasso{points} | Self::Polygon{points} => {
+                    let mut xs = crossings(points,y as f32 + 0.5);
+                    xs.sort_by(|a,b| a.total_cmp(b));
+                    let row_start = mask.len();
+                    mask.resize(row_start + width as usize,false);
+                    for pair in xs.chunks_exact(2) {
+                        // pixels whose center lies between the crossings
+                        let from = (pair[0] - 0.5).ceil().max(0.0) as usize;
+                        let to = ((pair[1] - 0.5).ceil().max(0.0) as usize).min(width as usize);
+                        for inside in mask[row_start..][from.min(to)..to].iter_mut() {
+                            *inside = true;
+                        }
+                    }
+                }
+                _ => {
+                    for x in 0..width {
+                        mask.push(self.contains(x,y));
+                    }
+                }
+            }
+        }
+        mask
+    }
+
+    // outline on screen, to_screen maps image pixels to screen positions
+    pub fn draw(&self,painter:&egui::Painter,to_screen:impl Fn([f32;2]) -> Pos2,stroke:egui::Stroke){
+        match self {
+            Self::Rectangle{min,max} => {
+                let rect = Rect::from_min_max(to_screen([min[0] as f32,min[1] as f32]),to_screen([max[0] as f32 + 1.0,max[1] as f32 + 1.0]));
+                painter.rect_stroke(rect,0.0,stroke,egui::StrokeKind::Middle);
+            }
+            Self::Ellipse{min,max} => {
+                let rect = Rect::from_min_max(to_screen([min[0] as f32,min[1] as f32]),to_screen([max[0] as f32 + 1.0,max[1] as f32 + 1.0]));
+                painter.add(egui::Shape::ellipse_stroke(rect.center(),rect.size() / 2.0,stroke));
+            }
+            Self::Lasso{points} | Self::Polygon{points} => {
+                painter.add(egui::Shape::closed_line(points.iter().map(|p| to_screen(*p)).collect(),stroke));
+            }
+        }
+    }
+}
+
+// x of every point where the closed outline crosses the horizontal line at y
+fn crossings(points:&[[f32;2]],y:f32) -> Vec<f32> {
+    let mut xs = vec![];
+    if points.len() < 3 {
+        return xs;
+    }
+    for (i,a) in points.iter().enumerate() {
+        let b = points[(i + 1) % points.len()];
+        // half open, so a vertex on the line is only counted once
+        if (a[1] <= y) != (b[1] <= y) {
+            let t = (y - a[1]) / (b[1] - a[1]);
+            xs.push(a[0] + t * (b[0] - a[0]));
+        }
+    }
+    xs
+}
+
+// the outline still being drawn, polygons also get their corners and a line to the cursor
+pub fn draw_draft(painter:&egui::Painter,tool:SelectionTool,points:&[Pos2],cursor:Option<Pos2>,stroke:egui::Stroke){
+    let mut line = points.to_vec();
+    if tool == SelectionTool::Polygon {
+        line.extend(cursor);
+        for point in points {
+            painter.circle_filled(*point,2.5,stroke.color);
+        }
+    }
+    if line.len() > 1 {
+        painter.add(egui::Shape::line(line,stroke));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mask_matches_contains() {
+        let selections = [
+            Selection::from_corners(SelectionTool::Rectangle,[7,2],[1,5]).unwrap(),
+            Selection::from_corners(SelectionTool::Ellipse,[0,0],[9,5]).unwrap(),
+            Selection::Polygon{points:vec![[1.0,0.5],[9.5,3.0],[1.0,7.0]]},
+            // crosses itself and leaves the image
+            Selection::Lasso{points:vec![[-2.0,-2.0],[12.0,8.0],[12.0,-1.0],[0.0,8.0]]},
+        ];
+        for selection in selections {
+            let mask = selection.mask(10,7);
+            let expected:Vec<bool> = (0..7).flat_map(|y| (0..10).map(move |x| (x,y))).map(|(x,y)| selection.contains(x,y)).collect();
+            assert_eq!(mask,expected,"{:?}",selection);
+            assert!(mask.iter().any(|m| *m),"{:?}",selection);
+        }
+    }
+
+    #[test]
+    fn rectangle_corners_are_inclusive() {
+        let selection = Selection::from_corners(SelectionTool::Rectangle,[2,1],[4,3]).unwrap();
+        assert_eq!(selection.mask(6,5).iter().filter(|m| **m).count(),9);
+    }
+}
@@ -0,0 +1,251 @@
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use egui::ColorImage;
+use image::Rgb;
+use itertools::Itertools;
+
+use super::WINDOW_ID;
+use super::AvarageingSystem;
+use super::{iris_color, iris_file_browser, iris_hdr, iris_image_creation, iris_image_io, iris_scan};
+
+pub struct FrameColors {
+    // position in the sorted folder, frames that failed to load leave a gap
+    pub index:usize,
+    pub file_name:String,
+    // rgb, share of the frame and pixel count, most dominant first
+    pub colors:Vec<(Rgb<u8>,f32,u32)>,
+}
+
+// builds a movie barcode from a folder of numbered frames
+pub struct SequenceWindow {
+    id:usize,
+    pub open:bool,
+    folder:String,
+    // name of the folder being scanned, exports are named after it
+    source_name:String,
+    settings:iris_scan::ScanSettings,
+    max_colors:usize,
+    scan_width:u32,
+    column_width:u32,
+    strip_height:u32,
+    pending:Vec<PathBuf>,
+    total:usize,
+    frames:Vec<FrameColors>,
+    strip:Option<iris_image_creation::BarcodeStrip>,
+    texture:Option<egui::TextureHandle>,
+    pub errors:Vec<String>,
+}
+
+impl SequenceWindow {
+    pub fn new() -> Self {
+        WINDOW_ID.with(|thread_id|{
+            let id = thread_id.get();
+            thread_id.set(id+1);
+            Self{
+                id,
+                open:true,
+                folder:String::new(),
+                source_name:String::new(),
+                settings:iris_scan::ScanSettings{
+                    avaraging_system:AvarageingSystem::MedianCuttin,
+                    color_gradation:0.1,
+                    color_dist_type:iris_color::ColorSpace::OkLab,
+                    clean_up_value:0.01,
+                    median_cut_amount:4,
+                    mean_schift_radius:10.0,
+                    median_method:iris_scan::MedianMethod::default(),
+                },
+                max_colors:5,
+                scan_width:160,
+                column_width:2,
+                strip_height:128,
+                pending:vec![],
+                total:0,
+                frames:vec![],
+                strip:None,
+                texture:None,
+                errors:vec![],
+            }
+        })
+    }
+
+    fn start(&mut self){
+        let folder = PathBuf::from(self.folder.trim());
+        let read_dir = match std::fs::read_dir(&folder) {
+            Ok(read_dir) => read_dir,
+            Err(err) => {
+                self.errors.push(format!("{}: {}",folder.display(),err));
+                return;
+            },
+        };
+        self.pending = read_dir.flatten().map(|e| e.path()).filter(|p| p.is_file() && iris_file_browser::is_image_file(p)).collect();
+        if self.pending.is_empty() {
+            self.errors.push(format!("{}: folder contains no images",folder.display()));
+            return;
+        }
+        self.pending.sort_by_cached_key(|p| frame_sort_key(p));
+        // frames are taken from the back of the list
+        self.pending.reverse();
+        self.total = self.pending.len();
+        self.source_name = std::path::absolute(&folder).ok().and_then(|f| f.file_name().map(|n| n.to_string_lossy().to_string())).unwrap_or_else(|| "movie".to_string());
+        self.frames.clear();
+        self.strip = None;
+        self.texture = None;
+    }
+
+    fn scan_frame(&mut self,index:usize,path:&Path){
+        let loaded = match iris_image_io::load_image(path) {
+            Ok(loaded) => loaded,
+            Err(err) => {
+                self.errors.push(format!("{}: {}",path.display(),err));
+                return;
+            },
+        };
+        let image = if iris_hdr::is_hdr(&loaded.image) {
+            image::DynamicImage::ImageRgba8(iris_hdr::tone_map(&loaded.image.to_rgba32f(),iris_hdr::ToneMapper::default(),0.0))
+        }else{
+            loaded.image
+        };
+        // frames are scanned small, a barcode column only needs the overall colors
+        let image = if image.width() > self.scan_width {image.thumbnail(self.scan_width,u32::MAX)} else {image};
+        let scan = iris_scan::scan(&image,&self.settings);
+        let colors = scan.color_list.iter()
+            .map(|(id,c)| (c.to_rgb(),scan.color_percent.get(id).copied().unwrap_or(0.0),scan.color_pixel_count.get(id).copied().unwrap_or(0)))
+            .sorted_by(|a,b| b.1.total_cmp(&a.1))
+            .take(self.max_colors)
+            .collect();
+        let file_name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        self.frames.push(FrameColors{index,file_name,colors});
+    }
+
+    fn generate_strip(&mut self){
+        let frames = self.frames.iter().map(|f| f.colors.iter().map(|c| (c.0,c.1)).collect()).collect();
+        let mut strip = iris_image_creation::BarcodeStrip::new(frames,self.column_width,self.strip_height);
+        strip.generate_strip();
+        self.strip = Some(strip);
+        self.texture = None;
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("frame,file,rank,hex,percent,pixel_count\n");
+        for f in self.frames.iter() {
+            for (rank,(rgb,share,count)) in f.colors.iter().enumerate() {
+                let _ = writeln!(csv,"{},\"{}\",{},#{:02x}{:02x}{:02x},{:.2},{}",f.index,f.file_name.replace('"',"\"\""),rank + 1,rgb[0],rgb[1],rgb[2],share * 100.0,count);
+            }
+        }
+        csv
+    }
+
+    fn export(&mut self){
+        let _ = std::fs::create_dir_all("./created_images");
+        let png = format!("./created_images/{}_barcode.png",self.source_name);
+        let csv = format!("./created_images/{}_barcode.csv",self.source_name);
+        if let Some(strip) = &self.strip && let Err(err) = strip.save_img(&png) {
+            self.errors.push(format!("{}: {}",png,err));
+        }
+        if let Err(err) = std::fs::write(&csv,self.to_csv()) {
+            self.errors.push(format!("{}: {}",csv,err));
+        }
+    }
+
+    pub fn show(&mut self,ctx:&egui::Context){
+        if !self.open {
+            return;
+        }
+        // one frame per update, so the ui stays responsive on long sequences
+        if let Some(path) = self.pending.pop() {
+            self.scan_frame(self.total - self.pending.len() - 1,&path);
+            if self.pending.is_empty() {
+                self.generate_strip();
+            }
+            ctx.request_repaint();
+        }
+        if self.texture.is_none() && let Some(strip) = &self.strip {
+            let size = [strip.img.width() as usize,strip.img.height() as usize];
+            self.texture = Some(ctx.load_texture(format!("movie_barcode_{}",self.id),ColorImage::from_rgb(size,&strip.img),egui::TextureOptions::NEAREST));
+        }
+        let mut window_open = self.open;
+        egui::Window::new("Movie Barcode").id(egui::Id::new(self.id)).open(&mut window_open).show(ctx,|ui|{
+            ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP),|ui|{
+                ui.label("Frame folder:");
+                ui.text_edit_singleline(&mut self.folder);
+            });
+            egui::ComboBox::from_label("Quantizer")
+                .selected_text(format!("{:?}",self.settings.avaraging_system))
+                .show_ui(ui,|ui|{
+                    ui.selectable_value(&mut self.settings.avaraging_system,AvarageingSystem::MedianCuttin,"Median Cutting");
+                    ui.selectable_value(&mut self.settings.avaraging_system,AvarageingSystem::DeltaE,"Delta E");
+                    ui.selectable_value(&mut self.settings.avaraging_system,AvarageingSystem::MedianColor,"Median Color");
+                });
+            match self.settings.avaraging_system {
+                AvarageingSystem::DeltaE => {
+                    ui.add(egui::Slider::new(&mut self.settings.color_gradation,0.0 ..= 2.0).text("Color Gradation (OkLab)"));
+                }
+                AvarageingSystem::MedianCuttin => {
+                    ui.add(egui::Slider::new(&mut self.settings.median_cut_amount,0 ..= 32).text("Median Cut amount"));
+                }
+                AvarageingSystem::MeanShift | AvarageingSystem::MedianColor => {}
+            }
+            ui.add(egui::Slider::new(&mut self.max_colors,1 ..= 16).text("Colors per frame"));
+            ui.add(egui::Slider::new(&mut self.scan_width,16 ..= 640).text("Scan width")).on_hover_text("Frames are downscaled to this width before scanning");
+            ui.add(egui::Slider::new(&mut self.column_width,1 ..= 16).text("Column width"));
+            ui.add(egui::Slider::new(&mut self.strip_height,16 ..= 512).text("Strip height"));
+            ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP),|ui|{
+                if self.pending.is_empty() {
+                    if ui.button("Build").clicked(){
+                        self.start();
+                    }
+                    if ui.add_enabled(!self.frames.is_empty(),egui::Button::new("Rebuild Strip")).on_hover_text("Redraw the strip with the new size, without scanning again").clicked(){
+                        self.generate_strip();
+                    }
+                }else{
+                    ui.add(egui::ProgressBar::new(self.frames.len() as f32/self.total as f32).text(format!("{}/{}",self.total - self.pending.len(),self.total)).desired_width(200.0));
+                    if ui.button("Cancel").clicked(){
+                        self.pending.clear();
+                        self.generate_strip();
+                    }
+                }
+            });
+            if let Some(texture) = &self.texture {
+                egui::ScrollArea::horizontal().show(ui,|ui|{
+                    ui.add(egui::Image::from_texture(texture));
+                });
+                if ui.button("Export").on_hover_text("Save the strip as PNG and the colors of every frame as CSV to ./created_images/").clicked(){
+                    self.export();
+                }
+            }
+        });
+        self.open = window_open;
+    }
+}
+
+// numbered frames sort by their number, so frame_9 comes before frame_10
+fn frame_sort_key(path:&Path) -> (String,u64,String) {
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let prefix = stem.trim_end_matches(|c:char| c.is_ascii_digit()).to_string();
+    let number = stem[prefix.len()..].parse().unwrap_or(0);
+    (prefix,number,stem)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_keeps_frame_numbers_after_failed_frames() {
+        let mut window = SequenceWindow::new();
+        // frame 1 failed to load
+        window.frames = vec![
+            FrameColors{index:0,file_name:"f0.png".to_string(),colors:vec![(Rgb([255,0,0]),0.75,3),(Rgb([0,0,255]),0.25,1)]},
+            FrameColors{index:2,file_name:"f\"2\".png".to_string(),colors:vec![(Rgb([0,255,0]),1.0,4)]},
+        ];
+        assert_eq!(window.to_csv(),"frame,file,rank,hex,percent,pixel_count\n0,\"f0.png\",1,#ff0000,75.00,3\n0,\"f0.png\",2,#0000ff,25.00,1\n2,\"f\"\"2\"\".png\",1,#00ff00,100.00,4\n");
+    }
+
+    #[test]
+    fn frames_sort_by_number() {
+        let mut paths = ["frame_10.png","frame_9.png","frame_1.png","credits.png"].map(PathBuf::from);
+        paths.sort_by_cached_key(|p| frame_sort_key(p));
+        assert_eq!(paths.map(|p| p.to_string_lossy().to_string()),["credits.png","frame_1.png","frame_9.png","frame_10.png"]);
+    }
+}
@@ -0,0 +1,95 @@
+use egui::Vec2;
+use image::DynamicImage;
+
+use super::{iris_auto, iris_metrics, iris_scan};
+
+#[derive(Debug,Clone,Copy)]
+pub struct SweepCell {
+    pub colors:usize,
+    // mean OkLab distance of the pixels to their color
+    pub mean_delta_e:f32,
+}
+
+// delta E scans over a grid of color gradation and clean up threshold, one cell per update
+pub struct ParameterSweep {
+    pub gradations:Vec<f32>,
+    pub clean_ups:Vec<f32>,
+    // one row per gradation
+    pub cells:Vec<Option<SweepCell>>,
+    image:DynamicImage,
+    settings:iris_scan::ScanSettings,
+    next:usize,
+}
+
+impl ParameterSweep {
+    pub fn new(image:&DynamicImage,settings:&iris_scan::ScanSettings,gradation_steps:usize,clean_up_steps:usize,clean_up_max:f32) -> Self {
+        let (coarse,fine) = iris_auto::gradation_range(settings.color_dist_type);
+        let gradations:Vec<f32> = (0..gradation_steps).map(|i| coarse * (fine / coarse).powf(i as f32 / (gradation_steps - 1).max(1) as f32)).collect();
+        let clean_ups:Vec<f32> = (0..clean_up_steps).map(|i| clean_up_max * i as f32 / (clean_up_steps - 1).max(1) as f32).collect();
+        Self{
+            cells:vec![None;gradations.len() * clean_ups.len()],
+            gradations,
+            clean_ups,
+            image:iris_auto::thumbnail(image),
+            settings:*settings,
+            next:0,
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.next >= self.cells.len()
+    }
+
+    pub fn progress(&self) -> f32 {
+        self.next as f32 / self.cells.len().max(1) as f32
+    }
+
+    // scans the next cell, returns false once every cell is done
+    pub fn step(&mut self) -> bool {
+        if self.is_done() {
+            return false;
+        }
+        let (row,column) = (self.next / self.clean_ups.len(),self.next % self.clean_ups.len());
+        let mut settings = self.settings;
+        settings.color_gradation = self.gradations[row];
+        settings.clean_up_value = self.clean_ups[column];
+        let scan = iris_scan::scan(&self.image,&settings);
+        let (mean_delta_e,_) = iris_auto::evaluate(&self.image,&scan);
+        self.cells[self.next] = Some(SweepCell{colors:scan.color_list.len(),mean_delta_e});
+        self.next += 1;
+        !self.is_done()
+    }
+
+    // grid of cluster count and mean error, colored by the error. returns the (gradation,clean up) of a clicked cell
+    pub fn show(&self,ui:&mut egui::Ui,id:egui::Id) -> Option<(f32,f32)> {
+        let max_e = self.cells.iter().flatten().map(|c| c.mean_delta_e).fold(f32::EPSILON,f32::max);
+        let mut clicked = None;
+        egui::Grid::new(id).spacing(Vec2::splat(2.0)).show(ui,|ui|{
+            ui.label("Gradation \\ Clean up");
+            for clean_up in self.clean_ups.iter() {
+                ui.label(format!("{:.3}",clean_up));
+            }
+            ui.end_row();
+            for (row,gradation) in self.gradations.iter().enumerate() {
+                ui.label(format!("{:.3}",gradation));
+                for (column,clean_up) in self.clean_ups.iter().enumerate() {
+                    let Some(cell) = self.cells[row * self.clean_ups.len() + column] else {
+                        ui.label("…");
+                        continue;
+                    };
+                    let [r,g,b] = iris_metrics::heat_color(cell.mean_delta_e,max_e);
+                    let fill = egui::Color32::from_rgb(r,g,b);
+                    let text_color = if (r as u32 + g as u32 + b as u32) > 384 {egui::Color32::BLACK} else {egui::Color32::WHITE};
+                    let text = egui::RichText::new(format!("{}\n{:.3}",cell.colors,cell.mean_delta_e)).color(text_color).small();
+                    if ui.add(egui::Button::new(text).fill(fill).min_size(Vec2::new(44.0,30.0)))
+                        .on_hover_text(format!("{} colors, mean ΔE {:.4}\nClick to scan with gradation {:.3} and clean up {:.3}",cell.colors,cell.mean_delta_e,gradation,clean_up))
+                        .clicked() {
+                        clicked = Some((*gradation,*clean_up));
+                    }
+                }
+                ui.end_row();
+            }
+        });
+        clicked
+    }
+}
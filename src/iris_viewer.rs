@@ -0,0 +1,92 @@
+use egui::{Pos2, Rect, Vec2};
+
+const MAX_VIEW_HEIGHT:f32 = 512.0;
+
+// zoom is relative to the fitted size, the offset moves the image center away from the viewport center
+#[derive(Clone,Copy)]
+pub struct ImageViewer {
+    pub zoom:f32,
+    pub offset:Vec2,
+    // clicks pick pixels instead of resetting the view
+    pub pick_mode:bool,
+    // drags draw a selection instead of panning
+    pub select_mode:bool,
+    // where the image was drawn last, to map between screen and image pixels
+    pub viewport:Rect,
+    pub image_rect:Rect,
+    scale:f32,
+}
+
+impl Default for ImageViewer {
+    fn default() -> Self {
+        Self{zoom:1.0,offset:Vec2::ZERO,pick_mode:false,select_mode:false,viewport:Rect::NOTHING,image_rect:Rect::NOTHING,scale:1.0}
+    }
+}
+
+impl ImageViewer {
+    pub fn reset(&mut self){
+        self.zoom = 1.0;
+        self.offset = Vec2::ZERO;
+    }
+
+    // image pixel coordinates of a screen position, not clamped to the image
+    pub fn pixel_at(&self,pos:Pos2) -> [f32;2] {
+        let pixel = (pos - self.image_rect.min) / self.scale;
+        [pixel.x,pixel.y]
+    }
+
+    pub fn screen_pos(&self,pixel:[f32;2]) -> Pos2 {
+        self.image_rect.min + Vec2::new(pixel[0],pixel[1]) * self.scale
+    }
+
+    // draws the texture with wheel zoom and drag to pan, returns the image pixel under the cursor
+    pub fn show(&mut self,ui:&mut egui::Ui,texture:&egui::TextureHandle,image_size:[u32;2]) -> (egui::Response,Option<[u32;2]>) {
+        let image_size = Vec2::new(image_size[0].max(1) as f32,image_size[1].max(1) as f32);
+        let width = ui.available_width().max(32.0);
+        let fit_scale = (width / image_size.x).min(MAX_VIEW_HEIGHT / image_size.y);
+        let (viewport,response) = ui.allocate_exact_size(Vec2::new(width,image_size.y * fit_scale),egui::Sense::click_and_drag());
+
+        if response.hovered() {
+            let scroll = ui.input(|i| i.smooth_scroll_delta.y);
+            if scroll != 0.0 && let Some(pointer) = response.hover_pos() {
+                let old_zoom = self.zoom;
+                self.zoom = (self.zoom * (scroll * 0.002).exp()).clamp(0.25,512.0);
+                // keep the point under the cursor in place
+                let from_center = pointer - viewport.center() - self.offset;
+                self.offset -= from_center * (self.zoom / old_zoom - 1.0);
+                ui.input_mut(|i| i.smooth_scroll_delta = Vec2::ZERO);
+            }
+        }
+        if response.dragged() && !self.select_mode {
+            self.offset += response.drag_delta();
+        }
+        if response.double_clicked() && !self.pick_mode && !self.select_mode {
+            self.reset();
+        }
+        let response = if self.pick_mode || self.select_mode {response.on_hover_cursor(egui::CursorIcon::Crosshair)} else {response};
+
+        let scale = fit_scale * self.zoom;
+        let image_rect = Rect::from_center_size(viewport.center() + self.offset,image_size * scale);
+        self.viewport = viewport;
+        self.image_rect = image_rect;
+        self.scale = scale;
+        let painter = ui.painter_at(viewport);
+        painter.image(texture.id(),image_rect,Rect::from_min_max(Pos2::ZERO,Pos2::new(1.0,1.0)),egui::Color32::WHITE);
+
+        let Some(pointer) = response.hover_pos() else {
+            return (response,None);
+        };
+        if !image_rect.contains(pointer) {
+            return (response,None);
+        }
+        let pixel = (pointer - image_rect.min) / scale;
+        let x = (pixel.x as u32).min(image_size.x as u32 - 1);
+        let y = (pixel.y as u32).min(image_size.y as u32 - 1);
+        if scale >= 8.0 {
+            // outline the hovered pixel once pixels are big enough to tell apart
+            let pixel_rect = Rect::from_min_size(image_rect.min + Vec2::new(x as f32,y as f32) * scale,Vec2::splat(scale));
+            painter.rect_stroke(pixel_rect,0.0,ui.visuals().selection.stroke,egui::StrokeKind::Inside);
+        }
+        (response,Some([x,y]))
+    }
+}
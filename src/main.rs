@@ -1,30 +1,91 @@
 use std::cmp::Ordering;
-use std::collections::HashSet;
 use std::fmt::Debug;
-use std::hash::Hash;
 use std::{collections::HashMap,path::PathBuf};
 use std::cell::Cell;
 use eframe::egui;
-use egui::{ColorImage, DroppedFile, Vec2};
-use image::{GenericImageView, ImageReader, Pixel, Rgb};
+use egui::{ColorImage, Vec2};
+use image::{DynamicImage, GenericImageView, Pixel, Rgb};
 use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 
 mod iris_color;
 mod iris_image_creation;
+mod iris_palette;
+mod iris_project;
+mod iris_history;
+mod iris_file_browser;
+mod iris_image_io;
+mod iris_scan;
+mod iris_sequence;
+mod iris_hdr;
+mod iris_viewer;
+mod iris_dither;
+mod iris_metrics;
+mod iris_auto;
+mod iris_sweep;
+mod iris_selection;
+mod iris_background;
+mod iris_filter;
 
 fn main() {
+    let paths:Vec<PathBuf> = std::env::args_os().skip(1).map(PathBuf::from).collect();
     let native_options = eframe::NativeOptions::default();
-    let _ = eframe::run_native("My egui App", native_options, Box::new(|cc| Ok(Box::new(MyEguiApp::new(cc)))));
+    let _ = eframe::run_native("My egui App", native_options, Box::new(|cc| Ok(Box::new(MyEguiApp::new(cc,paths)))));
 }
 struct ImageWindow {
     id:usize,
-    path:PathBuf,
+    path:Option<PathBuf>,
     img_editor:iris_image_creation::ImageEditor,
     main_img_size:[u32;2],
+    format_description:String,
     name:String,
+
+    frames:Vec<DynamicImage>,
+    frame_delays:Vec<u32>,
+    current_frame:usize,
+    scan_all_frames:bool,
+    frame_timeline:Vec<Vec<(Rgb<u8>,f32)>>,
+
+    hdr_source:Option<image::Rgba32FImage>,
+    tone_mapper:iris_hdr::ToneMapper,
+    exposure:f32,
+    scan_scene_linear:bool,
+
+    viewer:iris_viewer::ImageViewer,
+    sample_size:u32,
+    picked_colors:Vec<iris_color::PaletteColor>,
+    errors:Vec<String>,
     open:bool,
 
-    color_list:HashMap<u32,iris_color::AvarageRgb>,
+    // cluster id of every scanned pixel, frames follow each other for combined scans
+    labels:Vec<u32>,
+    pixel_totals:iris_scan::PixelTotals,
+    median_method:iris_scan::MedianMethod,
+    selection:Option<iris_selection::Selection>,
+    selection_tool:iris_selection::SelectionTool,
+    // image points of the selection being drawn, the start corner for rectangles and ellipses
+    selection_draft:Vec<[f32;2]>,
+    background:iris_background::BackgroundSettings,
+    filter:iris_filter::ColorFilter,
+    mask_style:iris_image_creation::MaskStyle,
+    mask_marks:Vec<bool>,
+    map_space:iris_color::ColorSpace,
+    dither:iris_dither::DitherMode,
+    quality:Option<iris_metrics::QualityMetrics>,
+    delta_e_map:Vec<f32>,
+    heatmap_max:f32,
+    auto_criterion:iris_auto::AutoCriterion,
+    auto_target:f32,
+    auto_steps:u32,
+    auto_search:Option<iris_auto::AutoSearch>,
+    auto_result:Option<iris_auto::AutoResult>,
+    // colors the scan of the full image with the chosen settings gave
+    auto_scan_colors:Option<usize>,
+    sweep:Option<iris_sweep::ParameterSweep>,
+    sweep_steps:[usize;2],
+    sweep_clean_up_max:f32,
+
+    color_list:HashMap<u32,iris_color::PaletteColor>,
     color_percent:HashMap<u32,f32>,
     color_pixel_count:HashMap<u32,u32>,
     color_gradation:f32,
@@ -36,6 +97,7 @@ struct ImageWindow {
     avaraging_system:AvarageingSystem,
 
     img: Option<iris_image_creation::HSLRect>,
+    img_texture:Option<egui::TextureHandle>,
     img_rect:Option<egui::TextureHandle>,
     img_bar:Option<egui::TextureHandle>,
     img_dispaly_generated:bool,
@@ -55,15 +117,17 @@ struct ImageWindow {
 
     avarage_lightness:f32,
     lightness_range:[f32;2],
+
+    commands:Vec<iris_history::Command>,
 }
 
-#[derive(Debug,PartialEq)]
+#[derive(Debug,PartialEq,Clone,Copy,Serialize,Deserialize)]
 enum CompareState {
     Percentages,
     Saturation,
 }
 
-#[derive(Debug,PartialEq)]
+#[derive(Debug,PartialEq,Clone,Copy,Serialize,Deserialize)]
 enum AvarageingSystem {
     DeltaE,
     MedianColor,
@@ -71,24 +135,36 @@ enum AvarageingSystem {
     MeanShift,
 }
 
-#[derive(Clone)]
-struct MedianCut {
-    median_color:[u8;3],
-    colors:Vec<([u8;3],[u32;2])>,
-    position:[u32;2],
-}
 thread_local!(static WINDOW_ID: Cell<usize> = Cell::new(0));
 
 
 impl ImageWindow {
-    fn new (new_file:DroppedFile)-> Self{
-        let path = new_file.path.clone().unwrap();
-        let mut name = path.file_name().unwrap().to_owned().to_string_lossy().to_string();
-        if path.file_stem().unwrap().to_str().unwrap().to_string().len() >= 10 {
-            name = path.file_stem().unwrap().to_str().unwrap().to_string()[0..10].to_string() + "." + &path.extension().unwrap().to_string_lossy()
-        }
-        let image = ImageReader::open(path.clone()).unwrap().decode().unwrap(); 
-        
+    fn new (path:PathBuf)-> Result<Self,iris_image_io::ImageLoadError>{
+        let name = iris_image_io::window_name(&path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default());
+        let loaded = iris_image_io::load_image(&path)?;
+        Ok(Self::from_image(loaded,name,Some(path)))
+    }
+    fn from_bytes(bytes:&[u8],name:&str) -> Result<Self,iris_image_io::ImageLoadError> {
+        let loaded = iris_image_io::load_image_from_memory(bytes,name)?;
+        Ok(Self::from_image(loaded,iris_image_io::window_name(name),None))
+    }
+    fn from_image(loaded:iris_image_io::LoadedImage,name:String,path:Option<PathBuf>) -> Self{
+        let mut format_description = format!("{}, {} bit {}",loaded.format_name(),loaded.bit_depth(),loaded.channel_name());
+        if !loaded.frames.is_empty() {
+            format_description += &format!(", {} frames",loaded.frames.len());
+        }
+        let frame_delays = loaded.frames.iter().map(|f| {
+            let (numer,denom) = f.delay().numer_denom_ms();
+            numer/denom.max(1)
+        }).collect();
+        let frames = loaded.frames.into_iter().map(|f| DynamicImage::ImageRgba8(f.into_buffer())).collect();
+        // float images are kept for re-tone-mapping, the editor works on the 8 bit result
+        let hdr_source = iris_hdr::is_hdr(&loaded.image).then(|| loaded.image.to_rgba32f());
+        let tone_mapper = iris_hdr::ToneMapper::default();
+        let image = match &hdr_source {
+            Some(hdr) => DynamicImage::ImageRgba8(iris_hdr::tone_map(hdr,tone_mapper,0.0)),
+            None => loaded.image,
+        };
         WINDOW_ID.with(|thread_id|{
             let id = thread_id.get();
             thread_id.set(id+1);
@@ -110,7 +186,7 @@ impl ImageWindow {
             let lightness_range = [0.0,0.0];
 
             let main_img_size = [image.width(),image.height()];
-            let img_editor = iris_image_creation::ImageEditor::new(path.clone());
+            let img_editor = iris_image_creation::ImageEditor::new(image,path.clone());
             ImageWindow{
                 path,
                 img_editor,
@@ -126,6 +202,7 @@ impl ImageWindow {
                 compare_state,
                 avaraging_system,
                 img: None,
+                img_texture: None,
                 img_bar: None,
                 img_rect: None,
                 img_dispaly_generated: false,
@@ -136,14 +213,731 @@ impl ImageWindow {
                 median_cut_amount:0,
                 mean_schift_radius:0_f32,
                 main_img_size,
+                format_description,
+                frames,
+                frame_delays,
+                current_frame:0,
+                scan_all_frames:false,
+                frame_timeline:vec![],
+                hdr_source,
+                tone_mapper,
+                exposure:0.0,
+                scan_scene_linear:false,
+                viewer:iris_viewer::ImageViewer::default(),
+                sample_size:1,
+                picked_colors:vec![],
+                errors:vec![],
+                labels:vec![],
+                pixel_totals:iris_scan::PixelTotals::default(),
+                median_method:iris_scan::MedianMethod::default(),
+                selection:None,
+                selection_tool:iris_selection::SelectionTool::default(),
+                selection_draft:vec![],
+                background:iris_background::BackgroundSettings::default(),
+                filter:iris_filter::ColorFilter::default(),
+                mask_style:iris_image_creation::MaskStyle::default(),
+                mask_marks:vec![],
+                map_space:iris_color::ColorSpace::OkLab,
+                dither:iris_dither::DitherMode::default(),
+                quality:None,
+                delta_e_map:vec![],
+                heatmap_max:0.1,
+                auto_criterion:iris_auto::AutoCriterion::default(),
+                auto_target:0.05,
+                auto_steps:16,
+                auto_search:None,
+                auto_result:None,
+                auto_scan_colors:None,
+                sweep:None,
+                sweep_steps:[5,4],
+                sweep_clean_up_max:0.05,
                 avarage_saturation,
                 saturation_range,
                 avarage_lightness,
                 lightness_range,
+                commands:vec![],
             }
 
         })
     }
+    fn to_project(&self) -> Option<iris_project::ProjectWindow> {
+        Some(iris_project::ProjectWindow{
+            path:self.path.clone()?,
+            avaraging_system:self.avaraging_system,
+            color_gradation:self.color_gradation,
+            color_dist_type:self.color_dist_type,
+            color_display_threshhold:self.color_display_threshhold,
+            clean_up_value:self.clean_up_value,
+            median_cut_amount:self.median_cut_amount,
+            mean_schift_radius:self.mean_schift_radius,
+            compare_state:self.compare_state,
+            mark_every_color:self.mark_every_color,
+            current_frame:self.current_frame,
+            scan_all_frames:self.scan_all_frames,
+            tone_mapper:self.tone_mapper,
+            exposure:self.exposure,
+            scan_scene_linear:self.scan_scene_linear,
+            median_method:self.median_method,
+            selection:self.selection.clone(),
+            background:self.background,
+            filter:self.filter.clone(),
+            pixel_totals:self.pixel_totals,
+            color_list:self.color_list.clone(),
+            color_percent:self.color_percent.clone(),
+            color_pixel_count:self.color_pixel_count.clone(),
+        })
+    }
+    fn from_project(project:iris_project::ProjectWindow) -> Result<Self,iris_image_io::ImageLoadError> {
+        let mut window = Self::new(project.path)?;
+        window.avaraging_system = project.avaraging_system;
+        window.color_gradation = project.color_gradation;
+        window.color_dist_type = project.color_dist_type;
+        window.color_display_threshhold = project.color_display_threshhold;
+        window.clean_up_value = project.clean_up_value;
+        window.median_cut_amount = project.median_cut_amount;
+        window.mean_schift_radius = project.mean_schift_radius;
+        window.compare_state = project.compare_state;
+        window.mark_every_color = project.mark_every_color;
+        window.scan_all_frames = project.scan_all_frames;
+        window.tone_mapper = project.tone_mapper;
+        window.exposure = project.exposure;
+        window.scan_scene_linear = project.scan_scene_linear;
+        window.median_method = project.median_method;
+        window.selection = project.selection;
+        window.background = project.background;
+        window.filter = project.filter;
+        window.apply_tone_mapping();
+        if project.current_frame < window.frames.len() {
+            window.set_frame(project.current_frame);
+        }
+        window.color_list = project.color_list;
+        window.color_percent = project.color_percent;
+        window.color_pixel_count = project.color_pixel_count;
+        window.pixel_totals = project.pixel_totals;
+        // older projects have no totals, the saved counts are all that is known of the scan
+        if window.pixel_totals.total == 0 {
+            window.pixel_totals.total = window.color_pixel_count.values().sum();
+        }
+        if !window.color_list.is_empty() {
+            window.get_img_data();
+        }
+        Ok(window)
+    }
+    fn scan_snapshot(&self) -> iris_history::ScanSnapshot {
+        iris_history::ScanSnapshot{
+            color_list:self.color_list.clone(),
+            color_percent:self.color_percent.clone(),
+            color_pixel_count:self.color_pixel_count.clone(),
+            pixel_totals:self.pixel_totals,
+        }
+    }
+    fn restore_scan(&mut self,snapshot:&iris_history::ScanSnapshot){
+        self.color_list = snapshot.color_list.clone();
+        self.color_percent = snapshot.color_percent.clone();
+        self.color_pixel_count = snapshot.color_pixel_count.clone();
+        self.pixel_totals = snapshot.pixel_totals;
+        // labels are not kept in the history, masks fall back to the nearest color
+        self.labels.clear();
+        self.clear_quality();
+        self.frame_timeline.clear();
+        if !self.color_list.is_empty() {
+            self.get_img_data();
+        }
+    }
+    fn finish_scan(&mut self,label:&'static str,before:iris_history::ScanSnapshot){
+        if self.scan_all_frames && self.frames.len() > 1 {
+            // combined scans run on the frames stacked vertically, map positions back onto a single frame
+            let height = self.main_img_size[1].max(1);
+            for c in self.color_list.values_mut() {
+                for sub_c in c.colors.iter_mut() {
+                    sub_c.position[1] %= height;
+                }
+            }
+            let stats = iris_scan::spatial_stats(&self.labels,self.main_img_size[0],height);
+            iris_scan::apply_spatial_stats(&mut self.color_list,&stats);
+        }
+        self.frame_timeline.clear();
+        self.clear_quality();
+        self.get_img_data();
+        self.record_scan(label,before);
+    }
+    fn record_scan(&mut self,label:&'static str,before:iris_history::ScanSnapshot){
+        let after = Box::new(self.scan_snapshot());
+        self.commands.push(iris_history::Command::Scan{window:self.id,label,before:Box::new(before),after});
+    }
+    fn marks(&self) -> Vec<bool> {
+        iris_history::collect_marks(self.color_list.iter().sorted_by_key(|(id,_)| **id).map(|(_,c)| c))
+    }
+    fn set_marks(&mut self,marks:&[bool]){
+        iris_history::apply_marks(self.color_list.iter_mut().sorted_by_key(|(id,_)| **id).map(|(_,c)| c),&mut marks.iter().copied());
+    }
+    fn scan_settings(&self) -> iris_scan::ScanSettings {
+        iris_scan::ScanSettings{
+            avaraging_system:self.avaraging_system,
+            color_gradation:self.color_gradation,
+            color_dist_type:self.color_dist_type,
+            clean_up_value:self.clean_up_value,
+            median_cut_amount:self.median_cut_amount,
+            mean_schift_radius:self.mean_schift_radius,
+            median_method:self.median_method,
+        }
+    }
+    fn show_selection(&mut self,ui:&mut egui::Ui,response:&egui::Response){
+        use iris_selection::{Selection, SelectionTool};
+        let [width,height] = self.main_img_size;
+        let clamp = |pixel:[f32;2]| [pixel[0].clamp(0.0,width as f32),pixel[1].clamp(0.0,height as f32)];
+        let to_pixel = |point:[f32;2]| [(point[0] as u32).min(width.saturating_sub(1)),(point[1] as u32).min(height.saturating_sub(1))];
+        let point = response.interact_pointer_pos().map(|p| clamp(self.viewer.pixel_at(p)));
+        if self.viewer.select_mode && let Some(point) = point {
+            match self.selection_tool {
+                SelectionTool::Rectangle | SelectionTool::Ellipse => {
+                    if response.drag_started() {
+                        self.selection_draft = vec![point];
+                    }
+                    if response.dragged() && let Some(start) = self.selection_draft.first() {
+                        self.selection = Selection::from_corners(self.selection_tool,to_pixel(*start),to_pixel(point));
+                    }
+                }
+                SelectionTool::Lasso => {
+                    if response.drag_started() {
+                        self.selection_draft = vec![point];
+                    }
+                    // a new point once the cursor moved a few screen pixels
+                    let moved = self.selection_draft.last().is_some_and(|last| self.viewer.screen_pos(*last).distance(self.viewer.screen_pos(point)) >= 3.0);
+                    if response.dragged() && moved {
+                        self.selection_draft.push(point);
+                    }
+                }
+                SelectionTool::Polygon => {
+                    if response.clicked() {
+                        self.selection_draft.push(point);
+                    }
+                    if response.double_clicked() {
+                        // the second click of the double click lands on the last corner again
+                        self.selection_draft.pop();
+                        if self.selection_draft.len() >= 3 {
+                            self.selection = Some(Selection::Polygon{points:std::mem::take(&mut self.selection_draft)});
+                        }
+                        self.selection_draft.clear();
+                    }
+                }
+            }
+        }
+        if response.drag_stopped() && self.selection_tool != SelectionTool::Polygon {
+            if self.selection_tool == SelectionTool::Lasso && self.selection_draft.len() >= 3 {
+                self.selection = Some(Selection::Lasso{points:std::mem::take(&mut self.selection_draft)});
+            }
+            self.selection_draft.clear();
+        }
+        let painter = ui.painter_at(self.viewer.viewport);
+        let stroke = egui::Stroke::new(1.5,ui.visuals().selection.stroke.color);
+        if let Some(selection) = &self.selection {
+            selection.draw(&painter,|p| self.viewer.screen_pos(p),stroke);
+        }
+        if matches!(self.selection_tool,SelectionTool::Lasso | SelectionTool::Polygon) && !self.selection_draft.is_empty() {
+            let draft:Vec<egui::Pos2> = self.selection_draft.iter().map(|p| self.viewer.screen_pos(*p)).collect();
+            iris_selection::draw_draft(&painter,self.selection_tool,&draft,response.hover_pos(),stroke);
+        }
+    }
+    // the image with every excluded pixel made transparent
+    fn region_image(&self,image:DynamicImage) -> DynamicImage {
+        match self.exclusions(&image) {
+            Some(exclusions) => iris_scan::mask_image(&image,&exclusions),
+            None => image,
+        }
+    }
+    fn apply_scan_settings(&mut self,settings:&iris_scan::ScanSettings){
+        self.color_gradation = settings.color_gradation;
+        self.median_cut_amount = settings.median_cut_amount;
+        self.mean_schift_radius = settings.mean_schift_radius;
+    }
+    fn show_auto_size(&mut self,ui:&mut egui::Ui){
+        egui::ComboBox::from_label("Criterion")
+            .selected_text(self.auto_criterion.to_string())
+            .show_ui(ui,|ui|{
+                for criterion in [iris_auto::AutoCriterion::Elbow,iris_auto::AutoCriterion::Silhouette,iris_auto::AutoCriterion::TargetDeltaE] {
+                    ui.selectable_value(&mut self.auto_criterion,criterion,criterion.to_string());
+                }
+            });
+        if self.auto_criterion == iris_auto::AutoCriterion::TargetDeltaE {
+            ui.add(egui::Slider::new(&mut self.auto_target,0.005 ..= 0.3).text("Mean ΔE (OkLab)")).on_hover_text("The smallest palette whose mean OkLab distance stays below this is used");
+        }
+        ui.add(egui::Slider::new(&mut self.auto_steps,3 ..= 64).text("Sizes to try"));
+        if let Some(search) = &self.auto_search {
+            let progress = search.progress();
+            ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP),|ui|{
+                ui.add(egui::ProgressBar::new(progress).show_percentage().desired_width(200.0));
+                if ui.button("Cancel").clicked(){
+                    self.auto_search = None;
+                }
+            });
+        }else if ui.button("Find size and Scan").on_hover_text("Runs the quantizer on a thumbnail for every size, then scans the image with the chosen one").clicked(){
+            self.auto_search = Some(iris_auto::AutoSearch::new(&self.region_image(self.scan_image()),&self.scan_settings(),self.auto_steps,self.auto_criterion,self.auto_target));
+        }
+        if let Some(result) = &self.auto_result {
+            iris_auto::plot(ui,result);
+            if let Some(best) = result.points.get(result.best) {
+                ui.label(format!("Chosen : {} colors on the thumbnail, mean ΔE {:.4}",best.colors,best.mean_delta_e));
+                if let Some(colors) = self.auto_scan_colors {
+                    ui.label(format!("Full image scan : {} colors",colors));
+                }
+            }
+        }
+    }
+    // one auto size step per update, the full image is scanned with the chosen size once the search is done
+    fn step_auto_search(&mut self,ctx:&egui::Context){
+        let Some(search) = &mut self.auto_search else {
+            return;
+        };
+        if search.step() {
+            ctx.request_repaint();
+            return;
+        }
+        let result = search.result();
+        self.auto_search = None;
+        self.auto_scan_colors = None;
+        if let Some(settings) = result.best_settings() {
+            self.apply_scan_settings(&settings);
+            let before = self.scan_snapshot();
+            self.scan();
+            self.finish_scan("Auto size scan",before);
+            self.auto_scan_colors = Some(self.color_list.len());
+        }
+        self.auto_result = Some(result);
+        ctx.request_repaint();
+    }
+    fn show_sweep(&mut self,ui:&mut egui::Ui){
+        ui.add(egui::Slider::new(&mut self.sweep_steps[0],2 ..= 10).text("Gradation steps"));
+        ui.add(egui::Slider::new(&mut self.sweep_steps[1],1 ..= 10).text("Clean up steps"));
+        ui.add(egui::Slider::new(&mut self.sweep_clean_up_max,0.0 ..= iris_scan::clean_up_max(self.color_dist_type)).text("Max clean up"));
+        let running = self.sweep.as_ref().is_some_and(|s| !s.is_done());
+        ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP),|ui|{
+            if running {
+                let progress = self.sweep.as_ref().map(|s| s.progress()).unwrap_or(0.0);
+                ui.add(egui::ProgressBar::new(progress).show_percentage().desired_width(200.0));
+                if ui.button("Cancel").clicked(){
+                    self.sweep = None;
+                }
+            }else if ui.button("Run Sweep").on_hover_text("Scans a thumbnail of the image for every combination").clicked(){
+                self.sweep = Some(iris_sweep::ParameterSweep::new(&self.region_image(self.scan_image()),&self.scan_settings(),self.sweep_steps[0],self.sweep_steps[1],self.sweep_clean_up_max));
+            }
+        });
+        let clicked = self.sweep.as_ref().and_then(|s| s.show(ui,egui::Id::new(self.id).with("sweep")));
+        if let Some((gradation,clean_up)) = clicked {
+            self.color_gradation = gradation;
+            self.clean_up_value = clean_up;
+            let before = self.scan_snapshot();
+            self.scan();
+            self.finish_scan("Delta E scan",before);
+        }
+    }
+    // pixels inside the selection, repeated for every frame of a combined scan
+    fn scan_mask(&self,image:&DynamicImage) -> Option<Vec<bool>> {
+        let selection = self.selection.as_ref()?;
+        let [width,height] = self.main_img_size;
+        let frame = selection.mask(width,height);
+        let frames = (image.height() / height.max(1)).max(1) as usize;
+        Some(frame.repeat(frames))
+    }
+    // pixels left out of scans, none if every pixel is scanned
+    fn exclusions(&self,image:&DynamicImage) -> Option<Vec<iris_scan::Exclusion>> {
+        let region = self.scan_mask(image);
+        let background = (self.background.mode != iris_background::BackgroundMode::Off)
+            .then(|| iris_background::detect(image,&self.background,self.main_img_size[1]));
+        let filtered = self.filter.is_active().then(|| self.filter.mask(image));
+        if region.is_none() && background.is_none() && filtered.is_none() {
+            return None;
+        }
+        Some((0..(image.width() * image.height()) as usize).map(|i| {
+            if region.as_ref().is_some_and(|r| !r[i]) {
+                iris_scan::Exclusion::Region
+            }else if background.as_ref().is_some_and(|b| b[i]) {
+                iris_scan::Exclusion::Background
+            }else if filtered.as_ref().is_some_and(|f| !f[i]) {
+                iris_scan::Exclusion::Filtered
+            }else{
+                iris_scan::Exclusion::None
+            }
+        }).collect())
+    }
+    fn show_background(&mut self,ui:&mut egui::Ui){
+        egui::ComboBox::from_label("Detection")
+            .selected_text(self.background.mode.to_string())
+            .show_ui(ui,|ui|{
+                for mode in iris_background::BackgroundMode::ALL {
+                    ui.selectable_value(&mut self.background.mode,mode,mode.to_string());
+                }
+            }).response.on_hover_text("Flood fill only takes background that touches the border,\nborder color also takes the same color inside the object");
+        ui.add(egui::Slider::new(&mut self.background.tolerance,0.0 ..= 0.3).text("Tolerance ΔE (OkLab)"));
+        ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP),|ui|{
+            let enabled = self.background.mode != iris_background::BackgroundMode::Off;
+            if ui.add_enabled(enabled,egui::Button::new("Show Background")).on_hover_text("Cover the detected background, scans leave it out").clicked(){
+                let background = iris_background::detect(&self.img_editor.image_reader,&self.background,self.main_img_size[1]);
+                self.img_editor.generate_background_mask(ui,&background);
+            }
+            if enabled && let Some(color) = iris_background::border_color(&self.img_editor.image_reader,self.main_img_size[1]) {
+                let (rect,_) = ui.allocate_exact_size(egui::Vec2::splat(16.0),egui::Sense::hover());
+                ui.painter().rect_filled(rect,2.0,egui::Color32::from_rgb(color[0],color[1],color[2]));
+                ui.label(format!("Border color #{:02x}{:02x}{:02x}",color[0],color[1],color[2]));
+            }
+        });
+    }
+    fn show_filters(&mut self,ui:&mut egui::Ui,id:egui::Id){
+        let mut remove = None;
+        for (i,range) in self.filter.ranges.iter_mut().enumerate() {
+            ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP),|ui|{
+                ui.checkbox(&mut range.enabled,"");
+                egui::ComboBox::from_id_salt(id.with(("range_mode",i)))
+                    .selected_text(range.mode.to_string())
+                    .show_ui(ui,|ui|{
+                        for mode in [iris_filter::RangeMode::Keep,iris_filter::RangeMode::Drop] {
+                            ui.selectable_value(&mut range.mode,mode,mode.to_string());
+                        }
+                    });
+                egui::ComboBox::from_id_salt(id.with(("range_channel",i)))
+                    .selected_text(range.channel.to_string())
+                    .show_ui(ui,|ui|{
+                        for channel in iris_filter::FilterChannel::ALL {
+                            if ui.selectable_value(&mut range.channel,channel,channel.to_string()).changed() {
+                                (range.min,range.max) = channel.limits();
+                            }
+                        }
+                    });
+                let (low,high) = range.channel.limits();
+                let speed = (high - low) / 200.0;
+                ui.add(egui::DragValue::new(&mut range.min).range(low ..= high).speed(speed).max_decimals(3));
+                ui.label("to");
+                ui.add(egui::DragValue::new(&mut range.max).range(low ..= high).speed(speed).max_decimals(3));
+                if ui.small_button("✖").clicked(){
+                    remove = Some(i);
+                }
+            });
+        }
+        if let Some(i) = remove {
+            self.filter.ranges.remove(i);
+        }
+        if ui.button("Add Range").on_hover_text("Hue arcs whose start is above their end wrap around 0°").clicked(){
+            self.filter.ranges.push(iris_filter::RangeFilter::default());
+        }
+        ui.separator();
+        let mut remove = None;
+        for (i,excluded) in self.filter.excluded.iter_mut().enumerate() {
+            ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP),|ui|{
+                ui.color_edit_button_srgb(&mut excluded.rgb);
+                ui.add(egui::Slider::new(&mut excluded.tolerance,0.0 ..= 0.2).text("Tolerance ΔE (OkLab)"));
+                if ui.small_button("✖").clicked(){
+                    remove = Some(i);
+                }
+            });
+        }
+        if let Some(i) = remove {
+            self.filter.excluded.remove(i);
+        }
+        ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP),|ui|{
+            if ui.button("Exclude Color").clicked(){
+                self.filter.excluded.push(iris_filter::ExcludedColor{rgb:[0,0,0],tolerance:0.03});
+            }
+            let marked:Vec<[u8;3]> = self.color_list.values().filter(|c| c.marked).map(|c| c.to_rgb().0).collect();
+            if ui.add_enabled(!marked.is_empty(),egui::Button::new("Exclude Marked Colors")).clicked(){
+                for rgb in marked {
+                    self.filter.excluded.push(iris_filter::ExcludedColor{rgb,tolerance:0.03});
+                }
+            }
+        });
+    }
+    fn scan(&mut self){
+        let image = self.scan_image();
+        let scan = iris_scan::scan_region(&image,&self.scan_settings(),self.exclusions(&image).as_deref());
+        self.color_list = scan.color_list;
+        self.color_percent = scan.color_percent;
+        self.color_pixel_count = scan.color_pixel_count;
+        self.labels = scan.labels;
+        self.pixel_totals = scan.totals;
+    }
+    fn scan_image(&self) -> DynamicImage {
+        if self.scan_scene_linear && let Some(hdr) = &self.hdr_source {
+            return DynamicImage::ImageRgba8(iris_hdr::scene_linear(hdr));
+        }
+        if !self.scan_all_frames || self.frames.len() <= 1 {
+            return self.img_editor.image_reader.clone();
+        }
+        let [width,height] = self.main_img_size;
+        let mut combined = image::RgbaImage::new(width,height * self.frames.len() as u32);
+        for (i,frame) in self.frames.iter().enumerate() {
+            image::imageops::replace(&mut combined,&frame.to_rgba8(),0,(height as usize * i) as i64);
+        }
+        DynamicImage::ImageRgba8(combined)
+    }
+    // start of the current frame in the label map, None when the labels do not belong to this image
+    fn label_offset(&self) -> Option<usize> {
+        let pixels = (self.img_editor.image_reader.width() * self.img_editor.image_reader.height()) as usize;
+        if self.frames.len() > 1 && self.labels.len() == pixels * self.frames.len() {
+            Some(pixels * self.current_frame)
+        }else if self.labels.len() == pixels {
+            Some(0)
+        }else{
+            None
+        }
+    }
+    // one entry per pixel, true if the pixel belongs to a marked color
+    fn cluster_mask(&self) -> Vec<bool> {
+        let selected:Vec<u32> = self.color_list.iter().filter(|(_,c)| c.marked).map(|(id,_)| *id).collect();
+        let image = &self.img_editor.image_reader;
+        let pixels = (image.width() * image.height()) as usize;
+        if selected.is_empty() {
+            return vec![false;pixels];
+        }
+        if let Some(offset) = self.label_offset() {
+            return self.labels[offset..offset + pixels].iter().map(|id| selected.contains(id)).collect();
+        }
+        // no labels after undo or loading a project, pixels go to the nearest color in OkLab
+        let palette:Vec<(u32,iris_color::OkLab)> = self.color_list.iter().map(|(id,c)| (*id,iris_color::OkLab::from_rgb(&c.to_rgb()))).collect();
+        let mut cache:HashMap<[u8;4],bool> = HashMap::new();
+        let mut mask = Vec::with_capacity(pixels);
+        for y in 0..image.height() {
+            for x in 0..image.width() {
+                let rgba = image.get_pixel(x,y).0;
+                if rgba[3] == 0 {
+                    mask.push(false);
+                    continue;
+                }
+                let is_selected = *cache.entry(rgba).or_insert_with(|| {
+                    let lab = iris_color::OkLab::from_rgb(&Rgb([rgba[0],rgba[1],rgba[2]]));
+                    palette.iter().min_by(|a,b| a.1.distance_to_lab_squared(&lab).total_cmp(&b.1.distance_to_lab_squared(&lab)))
+                        .is_some_and(|(id,_)| selected.contains(id))
+                });
+                mask.push(is_selected);
+            }
+        }
+        mask
+    }
+    fn generate_cluster_mask(&mut self,ui:&mut egui::Ui){
+        let mask = self.cluster_mask();
+        self.img_editor.generate_cluster_mask(ui,&mask,self.mask_style);
+        self.mask_marks = self.marks();
+    }
+    fn generate_palette_preview(&mut self,ui:&mut egui::Ui){
+        let palette:Vec<Rgb<u8>> = self.color_list.values().map(|c| c.to_rgb()).collect();
+        self.img_editor.generate_palette_mapped_img(ui,&palette,self.map_space,self.dither);
+    }
+    // compares the image with its palette preview, inside the selection only
+    fn measure_quality(&mut self){
+        let palette:Vec<Rgb<u8>> = self.color_list.values().map(|c| c.to_rgb()).collect();
+        let region = self.region_image(self.img_editor.image_reader.clone());
+        let original = region.to_rgba8();
+        let mapped = iris_dither::palette_map(&region,&palette,self.map_space,self.dither);
+        let (quality,delta_e_map) = iris_metrics::measure(&original,&mapped);
+        self.quality = Some(quality);
+        self.delta_e_map = delta_e_map;
+    }
+    fn clear_quality(&mut self){
+        self.quality = None;
+        self.delta_e_map.clear();
+    }
+    fn show_quality(&mut self,ui:&mut egui::Ui){
+        ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP),|ui|{
+            if ui.add_enabled(!self.color_list.is_empty(),egui::Button::new("Measure")).on_hover_text("Compare the image with the palette preview, using its color space and dithering").clicked(){
+                self.measure_quality();
+            }
+            if ui.add_enabled(!self.color_list.is_empty(),egui::Button::new("ΔE Heatmap")).on_hover_text("Show the OkLab ΔE of every pixel").clicked(){
+                if self.quality.is_none() {
+                    self.measure_quality();
+                }
+                self.img_editor.generate_delta_e_heatmap(ui,&self.delta_e_map,self.heatmap_max);
+            }
+            let heatmap_shown = matches!(self.img_editor.display_selection,iris_image_creation::DisplayOption::DeltaEHeatmap(_));
+            if ui.add(egui::Slider::new(&mut self.heatmap_max,0.01 ..= 0.5).text("White at ΔE")).changed() && heatmap_shown {
+                self.img_editor.generate_delta_e_heatmap(ui,&self.delta_e_map,self.heatmap_max);
+            }
+        });
+        let Some(quality) = self.quality else {
+            return;
+        };
+        egui::Grid::new(egui::Id::new(self.id).with("quality")).striped(true).show(ui,|ui|{
+            ui.label("");
+            ui.label("Mean");
+            ui.label("95th percentile");
+            ui.label("Max");
+            ui.end_row();
+            ui.label("ΔE OkLab");
+            ui.label(format!("{:.4}",quality.mean_oklab));
+            ui.label(format!("{:.4}",quality.p95_oklab));
+            ui.label(format!("{:.4}",quality.max_oklab));
+            ui.end_row();
+            ui.label("ΔE 2000");
+            ui.label(format!("{:.2}",quality.mean_de2000));
+            ui.label(format!("{:.2}",quality.p95_de2000));
+            ui.label(format!("{:.2}",quality.max_de2000));
+            ui.end_row();
+        });
+        ui.label(format!("PSNR : {:.2} dB  SSIM : {:.4}",quality.psnr,quality.ssim));
+    }
+    fn export_cluster_mask(&mut self){
+        let name = self.path.as_ref().and_then(|p| p.file_stem()).map(|n| n.to_string_lossy().to_string()).unwrap_or(self.name.clone());
+        let file = format!("./created_images/{}_cluster_mask.png",name);
+        let _ = std::fs::create_dir_all("./created_images");
+        if let Err(err) = self.img_editor.save_cluster_mask(&self.cluster_mask(),&file) {
+            self.errors.push(format!("{}: {}",file,err));
+        }
+    }
+    // averages the square around the pixel in linear light, transparent pixels are left out
+    fn sample_color(&self,pixel:[u32;2]) -> Rgb<u8> {
+        let image = &self.img_editor.image_reader;
+        let radius = self.sample_size/2;
+        let mut sum = [0.0_f64;3];
+        let mut count = 0;
+        for x in pixel[0].saturating_sub(radius)..=(pixel[0] + radius).min(image.width() - 1) {
+            for y in pixel[1].saturating_sub(radius)..=(pixel[1] + radius).min(image.height() - 1) {
+                let rgba = image.get_pixel(x,y);
+                if rgba[3] == 0 {
+                    continue;
+                }
+                for (s,c) in sum.iter_mut().zip(rgba.0) {
+                    *s += iris_color::gamma_expand(c as f64 / 255.0);
+                }
+                count += 1;
+            }
+        }
+        if count == 0 {
+            return image.get_pixel(pixel[0],pixel[1]).to_rgb();
+        }
+        Rgb(sum.map(|s| (iris_color::gamma_compress(s / count as f64) * 255.0).round().clamp(0.0,255.0) as u8))
+    }
+    fn show_pixel_inspector(&self,ui:&mut egui::Ui,pixel:Option<[u32;2]>){
+        let Some([x,y]) = pixel else {
+            ui.weak("Scroll to zoom, drag to pan, double click to reset");
+            return;
+        };
+        let rgba = self.img_editor.image_reader.get_pixel(x,y);
+        let rgb = rgba.to_rgb();
+        let hsl = iris_color::HSL::from_rgb(&rgb);
+        let ok_lab = iris_color::OkLab::from_rgb(&rgb);
+        let cie_lab = iris_color::CieLab::from_rgb(rgb);
+        ui.label(format!("Pixel : {} / {}",x,y));
+        ui.label(format!("RGB : {},{},{}  Alpha : {}",rgb[0],rgb[1],rgb[2],rgba[3]));
+        if let Some(hdr) = &self.hdr_source {
+            let [r,g,b,_] = hdr.get_pixel(x,y).0;
+            ui.label(format!("Linear : {:.3},{:.3},{:.3}",r,g,b));
+        }
+        ui.label(format!("HSL : {:.2},{:.2},{:.2}",hsl.h,hsl.s,hsl.l));
+        ui.label(format!("OkLab : {:.2},{:.2},{:.2}",ok_lab.l,ok_lab.a,ok_lab.b));
+        ui.label(format!("CieLab : {:.2},{:.2},{:.2}",cie_lab.l,cie_lab.a,cie_lab.b));
+        // the label of the last scan, otherwise the nearest scanned color in OkLab
+        let labeled = self.label_offset()
+            .map(|offset| self.labels[offset + (y * self.img_editor.image_reader.width() + x) as usize])
+            .and_then(|id| self.color_list.get_key_value(&id));
+        let cluster = labeled.or_else(|| self.color_list.iter().min_by(|a,b| {
+            let dist_a = iris_color::OkLab::from_rgb(&a.1.to_rgb()).distance_to_lab_squared(&ok_lab);
+            let dist_b = iris_color::OkLab::from_rgb(&b.1.to_rgb()).distance_to_lab_squared(&ok_lab);
+            dist_a.total_cmp(&dist_b)
+        }));
+        if let Some((id,c)) = cluster {
+            ui.with_layout(egui::Layout::left_to_right(egui::Align::Center),|ui|{
+                ui.label(format!("Cluster : #{} {}",id,c));
+                let (rect,_) = ui.allocate_exact_size(Vec2::splat(ui.text_style_height(&egui::TextStyle::Body)),egui::Sense::hover());
+                ui.painter().rect_filled(rect,2.0,egui::Color32::from_rgb(c.r,c.g,c.b));
+            });
+        }
+    }
+    fn apply_tone_mapping(&mut self){
+        if let Some(hdr) = &self.hdr_source {
+            self.img_editor.image_reader = DynamicImage::ImageRgba8(iris_hdr::tone_map(hdr,self.tone_mapper,self.exposure));
+            self.img_editor.display_selection = iris_image_creation::DisplayOption::Default;
+            self.img_texture = None;
+            self.clear_quality();
+        }
+    }
+    fn show_hdr(&mut self,ui:&mut egui::Ui){
+        let mut changed = false;
+        egui::ComboBox::from_label("Tone Mapper")
+            .selected_text(self.tone_mapper.to_string())
+            .show_ui(ui,|ui|{
+                for mapper in iris_hdr::ToneMapper::ALL {
+                    changed |= ui.selectable_value(&mut self.tone_mapper,mapper,mapper.to_string()).changed();
+                }
+            });
+        changed |= ui.add(egui::Slider::new(&mut self.exposure,-8.0 ..= 8.0).text("Exposure (EV)")).changed();
+        if changed {
+            self.apply_tone_mapping();
+        }
+        ui.checkbox(&mut self.scan_scene_linear,"Scan scene-linear values")
+            .on_hover_text("Scans use the linear values of the file, scaled so all but the brightest 0.1% of pixels fit below white,\ninstead of the tone mapped display image");
+    }
+    fn set_frame(&mut self,frame:usize){
+        self.current_frame = frame;
+        self.img_editor.image_reader = self.frames[frame].clone();
+        self.img_editor.display_selection = iris_image_creation::DisplayOption::Default;
+        self.img_texture = None;
+        self.clear_quality();
+    }
+    // share of every palette color in each frame, pixels are assigned to the nearest color in OkLab
+    fn generate_frame_timeline(&mut self){
+        let palette:Vec<(Rgb<u8>,iris_color::OkLab)> = self.color_list.values().map(|c| (c.to_rgb(),iris_color::OkLab::from_rgb(&c.to_rgb()))).collect();
+        self.frame_timeline.clear();
+        if palette.is_empty() {
+            return;
+        }
+        for frame in self.frames.iter() {
+            let mut counts = vec![0_u32;palette.len()];
+            let mut cache:HashMap<[u8;3],usize> = HashMap::new();
+            let mut pixel_count = 0;
+            for (_,_,rgba) in frame.pixels() {
+                if rgba.channels()[3] == 0 {
+                    continue;
+                }
+                let rgb = rgba.to_rgb();
+                let nearest = *cache.entry(rgb.0).or_insert_with(|| {
+                    let lab = iris_color::OkLab::from_rgb(&rgb);
+                    palette.iter().enumerate().min_by(|a,b| a.1.1.distance_to_lab_squared(&lab).total_cmp(&b.1.1.distance_to_lab_squared(&lab))).map(|(i,_)| i).unwrap_or(0)
+                });
+                counts[nearest] += 1;
+                pixel_count += 1;
+            }
+            let mut shares:Vec<(Rgb<u8>,f32)> = palette.iter().zip(counts).filter(|(_,count)| *count > 0).map(|(c,count)| (c.0,count as f32/pixel_count as f32)).collect();
+            shares.sort_by(|a,b| b.1.total_cmp(&a.1));
+            self.frame_timeline.push(shares);
+        }
+    }
+    fn show_animation(&mut self,ui:&mut egui::Ui){
+        let last_frame = self.frames.len() - 1;
+        let mut frame = self.current_frame;
+        ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP),|ui|{
+            if ui.add_enabled(frame > 0,egui::Button::new("<")).clicked(){
+                frame -= 1;
+            }
+            ui.add(egui::Slider::new(&mut frame,0 ..= last_frame).text("Frame"));
+            if ui.add_enabled(frame < last_frame,egui::Button::new(">")).clicked(){
+                frame += 1;
+            }
+            ui.label(format!("{} ms",self.frame_delays[frame]));
+        });
+        ui.checkbox(&mut self.scan_all_frames,"Scan all frames combined").on_hover_text("Scans use every frame instead of only the current one");
+        if ui.add_enabled(!self.color_list.is_empty(),egui::Button::new("Generate Timeline")).on_hover_text("Show how much of each scanned color every frame contains").clicked(){
+            self.generate_frame_timeline();
+        }
+        if self.frame_timeline.len() == self.frames.len() {
+            let (rect,response) = ui.allocate_exact_size(Vec2::new(ui.available_width(),64.0),egui::Sense::click());
+            let painter = ui.painter_at(rect);
+            let column_width = rect.width()/self.frames.len() as f32;
+            for (i,shares) in self.frame_timeline.iter().enumerate() {
+                let left = rect.left() + column_width * i as f32;
+                let mut bottom = rect.bottom();
+                for (rgb,share) in shares {
+                    let top = bottom - rect.height() * share;
+                    painter.rect_filled(egui::Rect::from_x_y_ranges(left ..= left + column_width,top ..= bottom),0.0,egui::Color32::from_rgb(rgb[0],rgb[1],rgb[2]));
+                    bottom = top;
+                }
+            }
+            let marker = egui::Rect::from_x_y_ranges(rect.left() + column_width * frame as f32 ..= rect.left() + column_width * (frame + 1) as f32,rect.y_range());
+            painter.rect_stroke(marker,0.0,ui.visuals().selection.stroke,egui::StrokeKind::Inside);
+            if let Some(pos) = response.interact_pointer_pos() {
+                frame = (((pos.x - rect.left())/column_width) as usize).min(last_frame);
+            }
+        }
+        if frame != self.current_frame {
+            self.set_frame(frame);
+        }
+    }
     fn remove_selected_color(&mut self){
         let mut id_to_remove:Vec<u32> = vec![];
         for (id,color) in self.color_list.iter(){
@@ -164,11 +958,9 @@ impl ImageWindow {
         }
         self.recalculate_color_precentage();
     }
+    // shares stay relative to every scanned pixel, removing a color doesn't make the others bigger
     fn recalculate_color_precentage(&mut self){
-        let mut pixel_count = 0;
-        for (_,count) in self.color_pixel_count.iter(){
-            pixel_count += count; 
-        }
+        let pixel_count = self.pixel_totals.scanned().max(1);
         for (id,percent) in self.color_percent.iter_mut(){
             if let Some(count) = self.color_pixel_count.get(id) {
                 *percent = *count as f32/pixel_count as f32;
@@ -216,6 +1008,8 @@ impl ImageWindow {
     }
     fn show (&mut self,ctx:&egui::Context){
         if self.open{
+            let marks_before = self.marks();
+            let commands_before = self.commands.len();
             if (self.img_bar.is_none()|| self.reload_hsl_bar) && self.img_dispaly_generated  {
                 if let Some(img) = &self.img {
                     self.img_bar = Some(ctx.load_texture("img_bar",ColorImage::from_rgb([img.size[0].try_into().unwrap(),(img.size[1]/4).try_into().unwrap()],&img.img_bar),Default::default()));
@@ -228,36 +1022,43 @@ impl ImageWindow {
                     self.reload_hsl_rect = false;
                 }
             }
+            if self.img_texture.is_none() {
+                // pixels stay sharp when zoomed in, downscaling is still smooth
+                let options = egui::TextureOptions{magnification:egui::TextureFilter::Nearest,..egui::TextureOptions::LINEAR};
+                let rgba = self.img_editor.image_reader.to_rgba8();
+                let image = ColorImage::from_rgba_unmultiplied([rgba.width() as usize,rgba.height() as usize],&rgba);
+                self.img_texture = Some(ctx.load_texture(format!("image_{}",self.id),image,options));
+            }
+            // one sweep cell per update, so the ui stays responsive
+            if let Some(sweep) = &mut self.sweep && sweep.step() {
+                ctx.request_repaint();
+            }
+            self.step_auto_search(ctx);
             let mut window_open = self.open;
-            egui::Window::new(self.name.clone()).id(egui::Id::new(self.id)).open(&mut window_open).show(ctx, |ui| {
+            let window_id = egui::Id::new(self.id);
+            egui::Window::new(self.name.clone()).id(window_id).open(&mut window_open).show(ctx, |ui| {
 
-                let string_path = "file://".to_owned() + self.path.to_str().unwrap();
-                match &mut self.img_editor.display_selection {
-                    iris_image_creation::DisplayOption::Default => {
-                        if self.main_img_size[0].max(self.main_img_size[1]) <= 128 {
-                            ui.add(
-                                egui::Image::new(string_path).texture_options(egui::TextureOptions::NEAREST ).shrink_to_fit()
-                            ); 
-                        }else{
-                            ui.add(
-                                egui::Image::new(string_path).shrink_to_fit()
-                            ); 
-                        }
-                    }
-                    iris_image_creation::DisplayOption::GrayScale(texture) => {
-                        if let Some(t) = texture {
-                            ui.add(
-                                egui::Image::from_texture(&*t).shrink_to_fit()
-                            );
-                        }
-                    }
-                    iris_image_creation::DisplayOption::DefaultWithMarker(texture) => {
-                        if let Some(t) = texture {
-                            ui.add(
-                                egui::Image::from_texture(&*t).shrink_to_fit()
-                            );
-                        }
+                // the mask follows the marked colors while it is shown
+                if matches!(self.img_editor.display_selection,iris_image_creation::DisplayOption::ClusterMask(_)) && self.mask_marks != self.marks() {
+                    self.generate_cluster_mask(ui);
+                }
+                let texture = match &self.img_editor.display_selection {
+                    iris_image_creation::DisplayOption::Default => self.img_texture.clone(),
+                    iris_image_creation::DisplayOption::GrayScale(texture) => texture.clone(),
+                    iris_image_creation::DisplayOption::DefaultWithMarker(texture) => texture.clone(),
+                    iris_image_creation::DisplayOption::ClusterMask(texture) => texture.clone(),
+                    iris_image_creation::DisplayOption::PaletteMapped(texture) => texture.clone(),
+                    iris_image_creation::DisplayOption::DeltaEHeatmap(texture) => texture.clone(),
+                    iris_image_creation::DisplayOption::BackgroundMask(texture) => texture.clone(),
+                };
+                if let Some(t) = texture {
+                    let (response,hovered) = self.viewer.show(ui,&t,self.main_img_size);
+                    self.show_selection(ui,&response);
+                    if self.viewer.pick_mode && response.clicked() && let Some(pixel) = hovered {
+                        let rgb = self.sample_color(pixel);
+                        self.picked_colors.push(iris_color::PaletteColor::from_rgb(rgb,pixel));
                     }
+                    self.show_pixel_inspector(ui,hovered);
                 }
                 egui::ScrollArea::horizontal().show(ui,|ui|{
                     ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP),|ui|{
@@ -270,9 +1071,93 @@ impl ImageWindow {
                         if ui.button("Generate default with Markers").clicked(){
                             self.img_editor.generate_default_with_markers(ui,self.main_img_size.clone(),self.color_list.clone());
                         } 
+                        if ui.button("Cluster Mask").on_hover_text("Highlight the pixels of the marked colors").clicked(){
+                            self.generate_cluster_mask(ui);
+                        }
+                        egui::ComboBox::from_id_salt(window_id.with("mask_style"))
+                            .selected_text(format!("{:?}",self.mask_style))
+                            .show_ui(ui,|ui|{
+                                for style in [iris_image_creation::MaskStyle::Dim,iris_image_creation::MaskStyle::Outline] {
+                                    if ui.selectable_value(&mut self.mask_style,style,format!("{:?}",style)).clicked() {
+                                        // regenerate on the next update if the mask is shown
+                                        self.mask_marks.clear();
+                                    }
+                                }
+                            });
+                        ui.separator();
+                        let mut remap = ui.add_enabled(!self.color_list.is_empty(),egui::Button::new("Palette Preview")).on_hover_text("Redraw the image with only the scanned colors").clicked();
+                        let mapped = matches!(self.img_editor.display_selection,iris_image_creation::DisplayOption::PaletteMapped(_));
+                        egui::ComboBox::from_id_salt(window_id.with("map_space"))
+                            .selected_text(format!("{:?}",self.map_space))
+                            .show_ui(ui,|ui|{
+                                for space in [iris_color::ColorSpace::Rgb,iris_color::ColorSpace::CieLab,iris_color::ColorSpace::OkLab] {
+                                    // settings only redraw a preview that is already shown
+                                    remap |= ui.selectable_value(&mut self.map_space,space,format!("{:?}",space)).clicked() && mapped;
+                                }
+                            }).response.on_hover_text("Color space of the nearest color search");
+                        egui::ComboBox::from_id_salt(window_id.with("dither"))
+                            .selected_text(self.dither.to_string())
+                            .show_ui(ui,|ui|{
+                                for mode in iris_dither::DitherMode::ALL {
+                                    remap |= ui.selectable_value(&mut self.dither,mode,mode.to_string()).clicked() && mapped;
+                                }
+                            }).response.on_hover_text("Dithering");
+                        if remap {
+                            self.generate_palette_preview(ui);
+                        }
+                        if ui.add_enabled(mapped,egui::Button::new("Export Preview")).on_hover_text("Save the palette preview as PNG to ./created_images/").clicked()
+                            && let Err(err) = self.img_editor.save_img() {
+                            self.errors.push(err);
+                        }
+                        ui.separator();
+                        if ui.button("Export Mask").on_hover_text("Save the pixels of the marked colors as a black and white PNG to ./created_images/").clicked(){
+                            self.export_cluster_mask();
+                        }
+                        ui.separator();
+                        if ui.toggle_value(&mut self.viewer.select_mode,"Select Region").on_hover_text(format!("{} to restrict scans and quality to a region",self.selection_tool.hint())).clicked(){
+                            self.viewer.pick_mode = false;
+                            self.selection_draft.clear();
+                        }
+                        egui::ComboBox::from_id_salt(window_id.with("selection_tool"))
+                            .selected_text(self.selection_tool.to_string())
+                            .show_ui(ui,|ui|{
+                                for tool in iris_selection::SelectionTool::ALL {
+                                    if ui.selectable_value(&mut self.selection_tool,tool,tool.to_string()).clicked(){
+                                        self.selection_draft.clear();
+                                    }
+                                }
+                            });
+                        if ui.add_enabled(self.selection.is_some(),egui::Button::new("Clear Region")).clicked(){
+                            self.selection = None;
+                            self.clear_quality();
+                        }
+                        ui.separator();
+                        if ui.toggle_value(&mut self.viewer.pick_mode,"Eyedropper").on_hover_text("Click the image to add the color under the cursor to your color palette").clicked(){
+                            self.viewer.select_mode = false;
+                        }
+                        egui::ComboBox::from_id_salt(window_id.with("sample_size"))
+                            .selected_text(format!("{0}x{0}",self.sample_size))
+                            .show_ui(ui,|ui|{
+                                for size in [1,3,5] {
+                                    ui.selectable_value(&mut self.sample_size,size,format!("{0}x{0}",size));
+                                }
+                            }).response.on_hover_text("Sample size, larger samples are averaged in linear light");
 
                     })
                 });
+                if self.hdr_source.is_some() {
+                    egui::CollapsingHeader::new("HDR").default_open(true).show(ui,|ui|{
+                        self.show_hdr(ui);
+                    });
+                }
+                if self.frames.len() > 1 {
+                    egui::CollapsingHeader::new("Animation").default_open(true).show(ui,|ui|{
+                        self.show_animation(ui);
+                    });
+                }
+                egui::CollapsingHeader::new("Quality").id_salt(window_id.with("quality")).show(ui,|ui|{
+                    self.show_quality(ui);
+                });
                 egui::ComboBox::from_label("Select Avaraging Technique")
                     .selected_text(format!("{:?}",self.avaraging_system))
                     .show_ui(ui,|ui|{
@@ -300,35 +1185,75 @@ impl ImageWindow {
                             iris_color::ColorSpace::Rgb => color_deg_max = 500.0,
                         }
                         ui.add(egui::Slider::new(&mut self.color_gradation,0.0 ..= color_deg_max).text("Color Gradation"));
-                        ui.add(egui::Slider::new(&mut self.clean_up_value,0.0 ..= 0.1).text("Clean up Threshold"))
-                            .on_hover_text("Minimum Color distance in OKLab, at which colors get merged after scan. \n (to clean up Duplicate Colors)");
+                        ui.add(egui::Slider::new(&mut self.clean_up_value,0.0 ..= iris_scan::clean_up_max(self.color_dist_type)).text("Clean up Threshold"))
+                            .on_hover_text("Color distance in the selected color space, at which colors get merged after scan. \nRepeated until no two colors are closer. (to clean up Duplicate Colors)");
                         if ui.add(egui::Button::new("Scan")).clicked(){
-                            self.scan_image_delta_e(ui);
-                            self.get_img_data();
+                            let before = self.scan_snapshot();
+                            self.scan();
+                            self.finish_scan("Delta E scan",before);
                         }
+                        egui::CollapsingHeader::new("Parameter sweep").id_salt(window_id.with("parameter_sweep")).show(ui,|ui|{
+                            self.show_sweep(ui);
+                        });
                     }
                     AvarageingSystem::MedianColor => {
+                        egui::ComboBox::from_label("Median")
+                            .selected_text(self.median_method.to_string())
+                            .show_ui(ui,|ui|{
+                                for method in [iris_scan::MedianMethod::PerChannel,iris_scan::MedianMethod::GeometricMedian,iris_scan::MedianMethod::Medoid] {
+                                    ui.selectable_value(&mut self.median_method,method,method.to_string());
+                                }
+                            }).response.on_hover_text("Per channel medians can give a color that is nowhere in the image,\nthe medoid is always one of the pixel colors");
+                        if self.selection.is_some() {
+                            ui.weak("Scanning the selected region only");
+                        }
                         if ui.button("Scan for Median Color").clicked(){
-                            self.scan_image_median_color(ui);
-                            self.get_img_data();
+                            let before = self.scan_snapshot();
+                            self.scan();
+                            self.finish_scan("Median color scan",before);
                         }
                     },
                     AvarageingSystem::MedianCuttin => {
                         ui.add(egui::Slider::new(&mut self.median_cut_amount,0 ..= 100).text("Median Cut amount")).on_hover_text("n Cuts result in n+1 colors");
                         if ui.button("Scan").clicked(){
-                            self.scan_image_median_cutting(ui);
-                            self.get_img_data();
+                            let before = self.scan_snapshot();
+                            self.scan();
+                            self.finish_scan("Median cut scan",before);
                         }
                     },
                     AvarageingSystem::MeanShift => {
                         ui.add(egui::Slider::new(&mut self.mean_schift_radius,0.0 ..= 100.0).text("Mean Shift Radius")).on_hover_text("OKLab range at which Colors get clustered Together");
                         if ui.button("Scan").clicked(){
-                            self.scan_image_mean_shift(ui);
-                            self.get_img_data();
+                            let before = self.scan_snapshot();
+                            self.scan();
+                            self.finish_scan("Mean shift scan",before);
                         }
                     }
                 }
+                if iris_auto::supports(self.avaraging_system) {
+                    egui::CollapsingHeader::new("Auto size").id_salt(window_id.with("auto_size")).show(ui,|ui|{
+                        self.show_auto_size(ui);
+                    });
+                }
+                egui::CollapsingHeader::new("Background").id_salt(window_id.with("background")).show(ui,|ui|{
+                    self.show_background(ui);
+                });
+                egui::CollapsingHeader::new("Filters").id_salt(window_id.with("filters")).show(ui,|ui|{
+                    self.show_filters(ui,window_id.with("filters"));
+                });
                 ui.separator();
+                if self.pixel_totals.total > 0 {
+                    let totals = self.pixel_totals;
+                    let mut text = format!("Scanned pixels : {}  Transparent : {}  Excluded : {}",totals.scanned(),totals.transparent,totals.excluded);
+                    let opaque = (totals.total - totals.transparent).max(1) as f32;
+                    if totals.background > 0 {
+                        text += &format!("  Background : {} ({:.1}% of opaque)",totals.background,totals.background as f32 / opaque * 100.0);
+                    }
+                    if totals.filtered > 0 {
+                        text += &format!("  Filtered : {} ({:.1}% of opaque)",totals.filtered,totals.filtered as f32 / opaque * 100.0);
+                    }
+                    ui.label(text).on_hover_text("Percentages are shares of the scanned pixels, background and filtered pixels are left out of them");
+                }
                 egui::ComboBox::from_label("Sorted by")
                     .selected_text(format!("{:?}", self.compare_state))
                     .show_ui(ui, |ui| {
@@ -343,11 +1268,15 @@ impl ImageWindow {
                         }
                     };
                     if ui.button("Remove selected Colors").clicked(){
+                        let before = self.scan_snapshot();
                         self.remove_selected_color();
+                        self.record_scan("Remove colors",before);
                     };
                 });
                 if ui.button("Switch to Most Saturated color").clicked() {
-                    self.switch_colors_to_saturarion(ui);
+                    let before = self.scan_snapshot();
+                    self.switch_colors_to_saturarion();
+                    self.record_scan("Switch to most saturated",before);
                 }
                 match self.compare_state {
                     CompareState::Percentages => {  // ----------PERCENTAGE GUI
@@ -367,7 +1296,7 @@ impl ImageWindow {
                                     let mut column_count = 0;
                                     for (id,c) in color_sorted.iter_mut(){
                                         if self.color_percent[id] >= self.color_display_threshhold{
-                                            iris_color::color_display(ui, c);
+                                            iris_color::color_display(ui, c,window_id.with(**id));
                                             column_count += 1;
                                             if column_count > (aw/(ui.available_width()+3.0)) as i32 {
                                                 ui.end_row();
@@ -383,7 +1312,7 @@ impl ImageWindow {
                                 ui.with_layout(egui::Layout::top_down(egui::Align::TOP).with_cross_justify(true),|ui|{
                                     for (id,c) in color_sorted.iter_mut(){
                                         if self.color_percent[id] >= self.color_display_threshhold || self.color_display_threshhold <= 0.0{
-                                            iris_color::color_display_percent(ui, c,self.color_percent[id].clone());
+                                            iris_color::color_display_percent(ui, c,window_id.with(**id),self.color_percent[id]);
                                         }
                                     }
                                 });
@@ -426,7 +1355,7 @@ impl ImageWindow {
                                     let mut column_count = 0;
                                     for (id,c) in color_sorted.iter_mut(){
                                         if self.color_percent[id] >= self.color_display_threshhold{
-                                            iris_color::color_display(ui,c);
+                                            iris_color::color_display(ui,c,window_id.with(**id));
                                             column_count += 1;
                                             if column_count > (aw/(ui.available_width()+3.0)) as i32 {
                                                 ui.end_row();
@@ -442,7 +1371,7 @@ impl ImageWindow {
                                 ui.with_layout(egui::Layout::top_down(egui::Align::TOP).with_cross_justify(true),|ui|{
                                     for (id,c) in &mut color_sorted{
                                         if self.color_percent[id] >= self.color_display_threshhold || self.color_display_threshhold == 0.0{
-                                            iris_color::color_display_percent(ui,c,self.color_percent[id]);
+                                            iris_color::color_display_percent(ui,c,window_id.with(**id),self.color_percent[id]);
                                         }
                                     }
                                 });
@@ -471,424 +1400,26 @@ impl ImageWindow {
                 }
                 egui::CollapsingHeader::new("Properties").show(ui,|ui|{
                     ui.label(format!("Size: {}x{}",self.main_img_size[0],self.main_img_size[1]));
+                    ui.label(format!("Format: {}",self.format_description));
                     ui.label(format!("Avarage Saturation: {:.2}%",self.avarage_saturation * 100.0));
                     ui.label(format!("Saturation Range:\n\tMax: {:.2}%\n\tMin: {:.2}%",self.saturation_range[0] * 100.0,self.saturation_range[1] * 100.0));
                     ui.label(format!("Avarage Lightness: {:.2}%",self.avarage_lightness * 100.0));
                     ui.label(format!("Lightness Range:\n\tMax: {:.2}%\n\tMin: {:.2}%",self.lightness_range[0] * 100.0,self.lightness_range[1] * 100.0));
                 });
-                for (_,color) in self.color_list.iter_mut(){
-                    if color.color_info_window_open {
-                        color.color_info_window_show(ui.ctx());
-                    }
+                for (id,color) in self.color_list.iter_mut(){
+                    color.color_info_window_show(ui.ctx(),window_id.with(*id));
                 }
             }); 
+            let marks_after = self.marks();
+            if self.commands.len() == commands_before && marks_before != marks_after {
+                self.commands.push(iris_history::Command::ScanMarks{window:self.id,before:marks_before,after:marks_after});
+            }
             self.open = window_open;
         }
     }    
-    fn switch_colors_to_saturarion(&mut self,ui:&mut egui::Ui){
+    fn switch_colors_to_saturarion(&mut self){
         for (_,c) in self.color_list.iter_mut(){
-           c.switch_to_most_saturated_color(ui); 
-        }
-    }
-    fn scan_image_median_color(&mut self,ui:&mut egui::Ui){
-
-        self.color_percent = HashMap::new();
-        self.color_list = HashMap::new();
-
-        let mut color_vec:Vec<(Rgb<u8>,[u32;2])> = vec![];
-
-        let image = ImageReader::open(self.path.clone()).unwrap().decode().unwrap(); 
-        let size = image.width() as f64 * image.height() as f64;
-
-        for (x,y,rgba) in image.pixels(){
-            if !(rgba.channels()[3]<= 0){
-                let rgb = rgba.to_rgb();
-                color_vec.push((rgb,[x,y]));
-            }
-        }
-         
-        color_vec.sort_by(|a,b| a.0.0[0].partial_cmp(&b.0.0[0]).unwrap());
-        let r:u8; 
-        let r_pos:[u32;2];
-        if color_vec.len() % 2 == 0 {
-            let upper = color_vec[color_vec.len()/2].0.0[0];
-            let lower = color_vec[(color_vec.len()/2)-1].0.0[0];
-
-            let upper_pos = color_vec[color_vec.len()/2].1;
-            let lower_pos = color_vec[color_vec.len()/2-1].1;
-
-            r = ((upper as u32 + lower as u32)/2).min(255) as u8;
-            r_pos = [(upper_pos[0] + lower_pos[0])/2,(upper_pos[1] + lower_pos[1])/2];
-        }else{
-            r = color_vec[(color_vec.len() as f32/2.0).ceil() as usize].0.0[0];
-            r_pos = color_vec[(color_vec.len() as f32/2.0).ceil() as usize].1;
-        }
-        color_vec.sort_by(|a,b| a.0.0[1].partial_cmp(&b.0.0[1]).unwrap());
-        let g:u8; 
-        let g_pos:[u32;2];
-        if color_vec.len() % 2 == 0 {
-            let upper = color_vec[color_vec.len()/2].0.0[1];
-            let lower = color_vec[(color_vec.len()/2)-1].0.0[1];
-
-            let upper_pos = color_vec[color_vec.len()/2].1;
-            let lower_pos = color_vec[color_vec.len()/2-1].1;
-
-            g = ((upper as u32 + lower as u32)/2).min(255) as u8;
-            g_pos = [(upper_pos[0] + lower_pos[0])/2,(upper_pos[1] + lower_pos[1])/2];
-        }else{
-            g = color_vec[(color_vec.len() as f32/2.0).ceil() as usize].0.0[1];
-            g_pos = color_vec[(color_vec.len() as f32/2.0).ceil() as usize].1;
-        }
-        color_vec.sort_by(|a,b| a.0.0[2].partial_cmp(&b.0.0[2]).unwrap());
-        let b:u8; 
-        let b_pos:[u32;2];
-        if color_vec.len() % 2 == 0 {
-            let upper = color_vec[color_vec.len()/2].0.0[2];
-            let lower = color_vec[(color_vec.len()/2)-1].0.0[2];
-
-            let upper_pos = color_vec[color_vec.len()/2].1;
-            let lower_pos = color_vec[color_vec.len()/2-1].1;
-
-            b = ((upper as u32 + lower as u32)/2).min(255) as u8;
-            b_pos = [(upper_pos[0] + lower_pos[0])/2,(upper_pos[1] + lower_pos[1])/2];
-        }else{
-            b = color_vec[(color_vec.len() as f32/2.0).ceil() as usize].0.0[2];
-            b_pos = color_vec[(color_vec.len() as f32/2.0).ceil() as usize].1;
-        }
-
-        let median_color:Rgb<u8> = Rgb::from([r,g,b]);
-        let median_pos:[u32;2] = [(r_pos[0]+g_pos[0]+b_pos[0])/3,(r_pos[0]+g_pos[0]+b_pos[0])/3];
-        let mut avarage_median = iris_color::AvarageRgb::from_rgb(median_color,median_pos);
-        avarage_median.generate_texture(ui);
-
-        self.color_list.insert(0,avarage_median);
-        self.color_percent.insert(0,1.0);
-        self.color_pixel_count.insert(0,size as u32);
-
-
-    }
-    fn get_median_color(&self,colors:&mut Vec<([u8;3],[u32;2])>) -> ([u8;3],[u32;2]) {
-        colors.sort_by(|a,b| a.0[0].partial_cmp(&b.0[0]).unwrap());
-        let r:u8; 
-        let r_pos:[u32;2];
-        if colors.len() == 1 {
-            return colors[0];
-        }
-        if colors.len() % 2 == 0 {
-            let upper = colors[colors.len()/2].0[0];
-            let lower = colors[(colors.len()/2)-1].0[0];
-
-            let upper_pos = colors[colors.len()/2].1;
-            let lower_pos = colors[colors.len()/2-1].1;
-
-            r = ((upper as u32 + lower as u32)/2).min(255) as u8;
-            r_pos = [(upper_pos[0] + lower_pos[0])/2,(upper_pos[1] + lower_pos[1])/2];
-        }else{
-            r = colors[(colors.len() as f32/2.0).ceil() as usize].0[0];
-            r_pos = colors[(colors.len() as f32/2.0).ceil() as usize].1;
-        }
-        colors.sort_by(|a,b| a.0[1].partial_cmp(&b.0[1]).unwrap());
-        let g:u8; 
-        let g_pos:[u32;2];
-        if colors.len() % 2 == 0 {
-            let upper = colors[colors.len()/2].0[1];
-            let lower = colors[(colors.len()/2)-1].0[1];
-
-            let upper_pos = colors[colors.len()/2].1;
-            let lower_pos = colors[colors.len()/2-1].1;
-
-            g = ((upper as u32 + lower as u32)/2).min(255) as u8;
-            g_pos = [(upper_pos[0] + lower_pos[0])/2,(upper_pos[1] + lower_pos[1])/2];
-        }else{
-            g = colors[(colors.len() as f32/2.0).ceil() as usize].0[1];
-            g_pos = colors[(colors.len() as f32/2.0).ceil() as usize].1;
-        }
-        colors.sort_by(|a,b| a.0[2].partial_cmp(&b.0[2]).unwrap());
-        let b:u8; 
-        let b_pos:[u32;2];
-        if colors.len() % 2 == 0 {
-            let upper = colors[colors.len()/2].0[2];
-            let lower = colors[(colors.len()/2)-1].0[2];
-
-            let upper_pos = colors[colors.len()/2].1;
-            let lower_pos = colors[colors.len()/2-1].1;
-
-            b = ((upper as u32 + lower as u32)/2).min(255) as u8;
-            b_pos = [(upper_pos[0] + lower_pos[0])/2,(upper_pos[1] + lower_pos[1])/2];
-        }else{
-            b = colors[(colors.len() as f32/2.0).ceil() as usize].0[2];
-            b_pos = colors[(colors.len() as f32/2.0).ceil() as usize].1;
-        }
-
-        let median_pos:[u32;2] = [(r_pos[0]+g_pos[0]+b_pos[0])/3,(r_pos[0]+g_pos[0]+b_pos[0])/3];
-
-        ([r,g,b],median_pos)
-    }
-
-
-    fn scan_image_mean_shift(&mut self, ui:&mut egui::Ui) {
-       let true_radius = self.mean_schift_radius/100.0; 
-
-        self.color_percent = HashMap::new();
-        self.color_list = HashMap::new();
-        self.color_pixel_count = HashMap::new();
-        let image = ImageReader::open(self.path.clone()).unwrap().decode().unwrap(); 
-        let _size = image.width() as f64 * image.height() as f64;
-
-        let mut color_rgb_values:HashSet<[u8;3]>= HashSet::new();
-
-        let mut end_points:HashSet<MeanShiftCursor> = HashSet::new();
-
-        for (_x,_y,rgba) in image.pixels(){
-            if !(rgba.channels()[3]<= 0){
-                let rgb = rgba.to_rgb();
-                if !color_rgb_values.contains(&rgb.0) {
-                    color_rgb_values.insert(rgb.0); 
-                }
-            }
-        }
-        for c in color_rgb_values.iter() {
-            // Rgb Value of Lab Position of ?Target/cursor
-            let mut current_pos = MeanShiftCursor::new(*c,true_radius);
-    
-            let mut looping = true;
-            while looping{
-                let mut added_new_color = false;
-                //current cursor pos as lab 
-                for secondary_color in color_rgb_values.iter() {
-                    if current_pos.inside_radius(*secondary_color){
-                        let added_color = current_pos.add_color(*secondary_color);
-                        if added_new_color == false {
-                            added_new_color = added_color;
-                        }
-                    }
-                }
-                if added_new_color {
-                    current_pos.move_to_color_avarage();
-                }else{
-                    let mut contains = false;
-                    for c in end_points.iter() {
-                        if c.is_same_as(&current_pos) {
-                            contains = true;
-                        }
-                    }
-                    if !contains {
-                        end_points.insert(current_pos.clone());
-                    }
-                    looping = false;
-                }
-            }
-            
-        }
-        for cursor in end_points {
-            let mut av_color = iris_color::AvarageRgb::from_rgb(Rgb::from(cursor.rgb_color),[0,0]);
-            av_color.generate_texture(ui);
-            for c in cursor.colors{
-                let mut sub_av_color = iris_color::AvarageRgb::from_rgb(Rgb::from(c),[0,0]);
-                sub_av_color.generate_texture(ui);
-                av_color.colors.push(sub_av_color);
-            }
-            let id = self.color_list.len() as u32;
-            self.color_list.insert(id, av_color);
-            self.color_percent.insert(id,1.0);
-        }
-    }
-
-    fn scan_image_median_cutting(&mut self, ui:&mut egui::Ui){
-        self.color_percent = HashMap::new();
-        self.color_list = HashMap::new();
-        self.color_pixel_count = HashMap::new();
-        let image = ImageReader::open(self.path.clone()).unwrap().decode().unwrap(); 
-        let _size = image.width() as f64 * image.height() as f64;
-
-        let mut color_rgb_values:HashMap<[u8;3],[u32;2]>= HashMap::new();
-       
-
-        for (x,y,rgba) in image.pixels(){
-            if !(rgba.channels()[3]<= 0){
-                let rgb = rgba.to_rgb();
-                if !color_rgb_values.contains_key(&rgb.0) {
-                    color_rgb_values.insert(rgb.0,[x,y]); 
-                }
-            }
-        }
-        let mut color_vec = color_rgb_values.into_iter().collect_vec();
-        let all_color_size = color_vec.len();
-        let result = self.get_median_color(&mut color_vec);
-        let mut cuts:Vec<MedianCut> = vec![MedianCut{median_color:result.0,colors:color_vec,position:result.1}];
-        for _ in 0..self.median_cut_amount {
-            let target = cuts.pop(); 
-            if let Some(mut t) = target {
-                let median_cut_pair = self.median_cut(&mut t.colors);
-                cuts.push(median_cut_pair[0].clone());
-                cuts.push(median_cut_pair[1].clone());
-            }
-            cuts.sort_by(|a,b| a.colors.len().partial_cmp(&b.colors.len()).unwrap());
-        }
-        for median_cut in cuts {
-            let mut avarage_median = iris_color::AvarageRgb::from_rgb(Rgb::from(median_cut.median_color),median_cut.position);
-            for c in median_cut.colors.clone().into_iter() {
-                if c.0 == median_cut.median_color {
-                    break;
-                }
-                let mut ac_buffer = iris_color::AvarageRgb::from_rgb(Rgb::from(c.0),c.1);
-                ac_buffer.generate_texture(ui);
-                avarage_median.colors.push(ac_buffer);
-            }
-            avarage_median.generate_texture(ui);
-            let key = self.color_list.len() as u32;
-            self.color_list.insert(key,avarage_median);
-            self.color_percent.insert(key,median_cut.colors.len() as f32/all_color_size as f32);
-        }
-
-    }
-
-
-    fn median_cut(&self,colors:&mut Vec<([u8;3],[u32;2])>) -> [MedianCut;2] {
-        // range = [max,min]
-        let mut r_range:[u8;2] = [0,u8::MAX];
-        let mut g_range:[u8;2] = [0,u8::MAX];
-        let mut b_range:[u8;2] = [0,u8::MAX];
-
-        for c in colors.iter() {
-            r_range[0] = r_range[0].max(c.0[0]);
-            r_range[1] = r_range[1].min(c.0[0]);
-
-            g_range[0] = g_range[0].max(c.0[1]);
-            g_range[1] = g_range[1].min(c.0[1]);
-
-            b_range[0] = b_range[0].max(c.0[2]);
-            b_range[1] = b_range[1].min(c.0[2]);
-        }
-        let biggest_range:usize;
-
-        let r_range_num = r_range[0] - r_range[1];
-        let g_range_num = g_range[0] - g_range[1];
-        let b_range_num = b_range[0] - b_range[1];
-
-        if  r_range_num > g_range_num && r_range_num > b_range_num {
-            biggest_range = 0;
-        }else if g_range_num > r_range_num && g_range_num > b_range_num {
-            biggest_range = 1;
-        }else {
-            biggest_range = 2;
-        }
-        if !biggest_range < 3 {
-            panic!();
-        }
-        colors.sort_by(|a,b| a.0[biggest_range].partial_cmp(&b.0[biggest_range]).unwrap());
-        let median = colors.len()/2;
-        let mut top_slice = colors[0..median].to_vec();
-        let mut bot_slice = colors[median..colors.len()].to_vec();
-        let top_color = self.get_median_color(&mut top_slice);
-        let bot_color = self.get_median_color(&mut bot_slice);
-        [MedianCut{colors:top_slice,median_color:top_color.0,position:top_color.1},MedianCut{colors:bot_slice,median_color:bot_color.0,position:bot_color.1}]
-    }
-
-    fn scan_image_delta_e(&mut self,ui:&mut egui::Ui){
-        let image = ImageReader::open(self.path.clone()).unwrap().decode().unwrap(); 
-        let size = image.width() as f64 * image.height() as f64;
-        self.color_percent = HashMap::new();
-        self.color_list = HashMap::new();
-        self.color_pixel_count = HashMap::new();
-        let mut max_dist = f32::MIN;
-        let mut min_dist = f32::MAX;
-        let mut transparent_pixels:f64 = 0.0;
-        for (x,y,rgba) in image.pixels(){
-            if !(rgba.channels()[3]<= 0){
-                let rgb = rgba.to_rgb();
-                let mut rgb_already_registered = false;
-                let mut closest_color_dist:f32 = f32::MAX;
-                let mut closest_color_key:Option<u32> = None;
-                if self.color_gradation >= 0.0 {
-                    for (key,value) in self.color_list.iter_mut(){
-                        let dist:f32;
-                        match self.color_dist_type{
-                            iris_color::ColorSpace::Rgb => dist = iris_color::rgb_distance(value.to_rgb(), rgb),
-                            iris_color::ColorSpace::CieLab => dist = {
-                                let lab_a = iris_color::CieLab::from_rgb(value.to_rgb());
-                                let lab_b = iris_color::CieLab::from_rgb(rgb);
-                                lab_a.distance_to_lab(&lab_b)
-                            },
-                            iris_color::ColorSpace::OkLab => dist = {
-                                let lab_a = iris_color::OkLab::from_rgb(&value.to_rgb());
-                                let lab_b = iris_color::OkLab::from_rgb(&rgb);
-                                // max_dist = max_dist.max(lab_a.b);
-                                // min_dist = min_dist.min(lab_a.b);
-                                lab_a.distance_to_lab(&lab_b)
-                            },
-                        }
-                        max_dist = max_dist.max(dist);
-                        min_dist = min_dist.min(dist);
-                        if dist <= self.color_gradation{
-                            if closest_color_dist > dist {
-                                closest_color_dist = dist;
-                                closest_color_key = Some(*key);
-                            }
-                            rgb_already_registered = true;
-                        }
-                    }
-                }
-                if !rgb_already_registered {
-                    self.color_percent.insert(self.color_list.len() as u32,(1.0/size)as f32);
-                    self.color_pixel_count.insert(self.color_list.len() as u32, 1);
-                    self.color_list.insert(self.color_list.len() as u32,iris_color::AvarageRgb::from_rgb(rgb,[x,y]));
-                }else if let Some(cck) = closest_color_key{
-                    if let Some(value) = self.color_list.get_mut(&cck){
-                        if self.color_gradation > 0.0 {
-                            value.avarage_with_rgb(&rgb,[x,y]);
-                        }
-                        if let Some(percent) = self.color_percent.get_mut(&cck){
-                            *percent += (1.0/size) as f32;
-                        }
-                        if let Some(count) = self.color_pixel_count.get_mut(&cck){
-                            *count += 1;
-                        }
-                    }
-                }
-            }else{
-                transparent_pixels += 1.0;
-            }
-        }
-        // println!("{} >> {}",max_dist,min_dist);
-        for (_,p) in self.color_percent.iter_mut(){
-            *p = ((*p as f64 *size)/(size-transparent_pixels)) as f32;
-        }
-
-        for (_id,c) in self.color_list.iter_mut(){
-            c.generate_texture(ui);
-            for sub_c in c.colors.iter_mut() {
-                sub_c.generate_texture(ui);
-            }
-        }
-        self.clean_up();
-    }
-    fn clean_up(&mut self) {
-        let mut id_remove = vec![];
-        let id_list = self.color_list.clone();
-        for ids in id_list.keys().into_iter().combinations(2){
-            if !(id_remove.contains(&ids[0]) || id_remove.contains(&ids[1])){
-                if iris_color::OkLab::from_rgb(&self.color_list[ids[0]].to_rgb()).distance_to_lab(&iris_color::OkLab::from_rgb(&self.color_list[ids[1]].to_rgb())) <= self.clean_up_value{
-                    let other_value = self.color_list[ids[1]].clone();
-                    let other_percent = self.color_percent[ids[1]].clone();
-                    let other_pixel = self.color_pixel_count[ids[1]].clone();
-                    if let Some(value) = self.color_list.get_mut(ids[0]){
-                        value._avarage(&other_value);
-                    }
-                    if let Some(value) = self.color_percent.get_mut(ids[0]){
-                        *value += other_percent;
-                    }
-                    if let Some(value) = self.color_pixel_count.get_mut(ids[0]){
-                        *value = value.checked_add(other_pixel).unwrap_or(u32::MAX);
-                    }
-                    id_remove.push(ids[1]);
-                } 
-            }
-        }
-        for id in id_remove {
-            self.color_list.remove(id);
-            self.color_percent.remove(id);
-            self.color_pixel_count.remove(id);
+           c.switch_to_most_saturated_color(); 
         }
     }
     fn get_img_data(&mut self){
@@ -922,28 +1453,76 @@ impl ImageWindow {
 struct MyEguiApp {
     image_windows:Vec<ImageWindow>,
     image_creation_windows:Vec<iris_image_creation::ImageCreator>,
+    sequence_windows:Vec<iris_sequence::SequenceWindow>,
     color_to_add:[f32;3],
-    global_colors:Vec<iris_color::AvarageRgb>,
+    global_colors:Vec<iris_color::PaletteColor>,
     compare_window:Vec<ColorCompareWindow>,
     mark_every_color:bool,
+    palette_import_path:String,
+    errors:Vec<String>,
+    export_format:iris_palette::ExportFormat,
+    export_naming:iris_palette::ExportNaming,
+    project_path:String,
+    history:iris_history::History,
+    file_browser:iris_file_browser::FileBrowser,
+    recent_files:Vec<PathBuf>,
 }
 
 impl MyEguiApp {
     fn has_image_window(&self,path:PathBuf) -> bool{
         for im in &self.image_windows{
-            if path == im.path{
+            if Some(&path) == im.path.as_ref(){
                 return true;
             }
         }
         return false;
     }
-    fn new(cc: &eframe::CreationContext<'_>) -> Self {
+    fn new(cc: &eframe::CreationContext<'_>,paths:Vec<PathBuf>) -> Self {
         // Customize egui here with cc.egui_ctx.set_fonts and cc.egui_ctx.set_visuals.
-        // Restore app state using cc.storage (requires the "persistence" feature).
         // Use the cc.gl (a glow::Context) to create graphics shaders and buffers that you can use
         // for e.g. egui::PaintCallback.
         egui_extras::install_image_loaders(&cc.egui_ctx);
-        Self::default()
+        let recent_files = cc.storage.and_then(|s| eframe::get_value(s,iris_file_browser::RECENT_FILES_KEY)).unwrap_or_default();
+        let mut app = Self{
+            project_path:format!("project.{}",iris_project::PROJECT_EXTENSION),
+            recent_files,
+            ..Self::default()
+        };
+        for path in paths {
+            app.open_path(path);
+        }
+        app
+    }
+    fn open_path(&mut self,path:PathBuf){
+        if !path.exists() {
+            self.errors.push(format!("{}: file not found",path.display()));
+            return;
+        }
+        // files that failed to open stay out of the recent files
+        if iris_palette::is_palette_file(&path) {
+            if !self.import_palette(path.clone()) {
+                return;
+            }
+        }else if iris_project::is_project_file(&path) {
+            if !self.load_project(path.clone()) {
+                return;
+            }
+        }else if !self.has_image_window(path.clone()) {
+            match ImageWindow::new(path.clone()) {
+                Ok(window) => self.image_windows.push(window),
+                Err(err) => {
+                    self.errors.push(format!("{}: {}",path.display(),err));
+                    return;
+                },
+            }
+        }
+        iris_file_browser::add_recent_file(&mut self.recent_files,path);
+    }
+    fn open_bytes(&mut self,bytes:&[u8],name:&str){
+        match ImageWindow::from_bytes(bytes,name) {
+            Ok(window) => self.image_windows.push(window),
+            Err(err) => self.errors.push(format!("{}: {}",name,err)),
+        }
     }
     fn get_global_selected_colors(&mut self){
         for iw in self.image_windows.iter() {
@@ -967,8 +1546,8 @@ impl MyEguiApp {
             }
         }
     }
-    fn get_selected_colors(&self)->Vec<iris_color::AvarageRgb>{
-        let mut color_to_return:Vec<iris_color::AvarageRgb> = vec![]; 
+    fn get_selected_colors(&self)->Vec<iris_color::PaletteColor>{
+        let mut color_to_return:Vec<iris_color::PaletteColor> = vec![]; 
         for c in self.global_colors.iter() {
             if c.marked {
                 color_to_return.push(c.clone());
@@ -981,6 +1560,106 @@ impl MyEguiApp {
         }
         color_to_return
     }
+    // same colors as get_selected_colors, without cloning them
+    fn has_selected_colors(&self) -> bool {
+        self.global_colors.iter().any(|c| c.marked || c.colors.iter().any(|sub_c| sub_c.marked && !self.global_colors.contains(sub_c)))
+    }
+    fn record_palette(&mut self,label:&'static str,before:Vec<iris_color::PaletteColor>){
+        let unchanged = before.len() == self.global_colors.len()
+            && before.iter().zip(self.global_colors.iter()).all(|(a,b)| a.to_rgb() == b.to_rgb() && a.marked == b.marked);
+        if unchanged {
+            return;
+        }
+        let after = self.global_colors.clone();
+        self.history.push(iris_history::Command::Palette{label,before,after});
+    }
+    fn undo(&mut self){
+        if let Some(command) = self.history.undo() {
+            Self::apply_command(&mut self.image_windows,&mut self.global_colors,command,true);
+        }
+    }
+    fn redo(&mut self){
+        if let Some(command) = self.history.redo() {
+            Self::apply_command(&mut self.image_windows,&mut self.global_colors,command,false);
+        }
+    }
+    fn apply_command(image_windows:&mut [ImageWindow],global_colors:&mut Vec<iris_color::PaletteColor>,command:&iris_history::Command,undo:bool){
+        match command {
+            iris_history::Command::Scan{window,before,after,..} => {
+                if let Some(w) = image_windows.iter_mut().find(|w| w.id == *window) {
+                    w.restore_scan(if undo {before} else {after});
+                }
+            },
+            iris_history::Command::ScanMarks{window,before,after} => {
+                if let Some(w) = image_windows.iter_mut().find(|w| w.id == *window) {
+                    w.set_marks(if undo {before} else {after});
+                }
+            },
+            iris_history::Command::Palette{before,after,..} => {
+                *global_colors = if undo {before.clone()} else {after.clone()};
+            },
+            iris_history::Command::PaletteMarks{before,after} => {
+                let marks = if undo {before} else {after};
+                iris_history::apply_marks(global_colors.iter_mut(),&mut marks.iter().copied());
+            },
+        }
+    }
+    // returns false when the palette could not be read
+    fn import_palette(&mut self,path:PathBuf) -> bool {
+        match iris_palette::import_palette(&path) {
+            Ok(colors) => {
+                let before = self.global_colors.clone();
+                for rgb in colors {
+                    let color = iris_color::PaletteColor::from_rgb(rgb,[0,0]);
+                    if !self.global_colors.contains(&color){
+                        self.global_colors.push(color);
+                    }
+                }
+                self.record_palette("Import palette",before);
+                true
+            },
+            Err(err) => {
+                self.errors.push(format!("{}: {}",path.display(),err));
+                false
+            },
+        }
+    }
+    fn save_project(&mut self,path:PathBuf){
+        let mut windows = vec![];
+        for w in self.image_windows.iter() {
+            match w.to_project() {
+                Some(window) => windows.push(window),
+                None => self.errors.push(format!("{}: not saved in the project, it was not opened from a file",w.name)),
+            }
+        }
+        let global_colors = self.global_colors.clone();
+        if let Err(err) = iris_project::Project::new(windows,global_colors).save(&path) {
+            self.errors.push(format!("{}: {}",path.display(),err));
+        }
+    }
+    // returns false when the project file could not be read
+    fn load_project(&mut self,path:PathBuf) -> bool {
+        match iris_project::Project::load(&path) {
+            Ok(project) => {
+                self.image_windows.clear();
+                self.history.clear();
+                for w in project.windows {
+                    let image_path = w.path.clone();
+                    match ImageWindow::from_project(w) {
+                        Ok(window) => self.image_windows.push(window),
+                        Err(err) => self.errors.push(format!("{}: image {}: {}",path.display(),image_path.display(),err)),
+                    }
+                }
+                self.global_colors = project.global_colors;
+                self.project_path = path.to_string_lossy().to_string();
+                true
+            },
+            Err(err) => {
+                self.errors.push(format!("{}: {}",path.display(),err));
+                false
+            },
+        }
+    }
     fn remove_selected_colors(&mut self){
         let mut id_to_remove:Vec<usize> = vec![];
         for (id,c) in self.global_colors.iter().enumerate() {
@@ -995,20 +1674,117 @@ impl MyEguiApp {
     }
 }
 
+// keyed by color so open views stay with their color when others are removed, repeats of a color are numbered
+fn global_color_ids(colors:&[iris_color::PaletteColor]) -> Vec<egui::Id> {
+    let mut seen:HashMap<[u8;3],usize> = HashMap::new();
+    colors.iter().map(|c| {
+        let n = seen.entry(c.to_rgb().0).or_default();
+        *n += 1;
+        egui::Id::new("global_colors").with((c.to_rgb().0,*n))
+    }).collect()
+}
+
 impl eframe::App for MyEguiApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        egui::TopBottomPanel::top("ToolBar").show(ctx, |ui| {
-            if ui.add(egui::Button::new("Image Creation")).clicked(){
-                self.image_creation_windows.push(iris_image_creation::ImageCreator::new());
+        if !ctx.wants_keyboard_input() {
+            let redo = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND | egui::Modifiers::SHIFT,egui::Key::Z);
+            let undo = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND,egui::Key::Z);
+            if ctx.input_mut(|i| i.consume_shortcut(&redo)) {
+                self.redo();
+            }else if ctx.input_mut(|i| i.consume_shortcut(&undo)) {
+                self.undo();
             }
+        }
+        egui::TopBottomPanel::top("ToolBar").show(ctx, |ui| {
+            ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP),|ui|{
+                ui.menu_button("File",|ui| {
+                    if ui.button("Open…").clicked(){
+                        self.file_browser.open();
+                        ui.close_menu();
+                    }
+                    ui.add_enabled_ui(!self.recent_files.is_empty(),|ui| {
+                        ui.menu_button("Open Recent",|ui| {
+                            let mut to_open = None;
+                            for path in self.recent_files.iter() {
+                                if ui.button(path.to_string_lossy()).clicked(){
+                                    to_open = Some(path.clone());
+                                }
+                            }
+                            ui.separator();
+                            if ui.button("Clear Recent").clicked(){
+                                self.recent_files.clear();
+                                ui.close_menu();
+                            }
+                            if let Some(path) = to_open {
+                                self.open_path(path);
+                                ui.close_menu();
+                            }
+                        });
+                    });
+                });
+                ui.separator();
+                let undo_label = self.history.next_undo();
+                if ui.add_enabled(undo_label.is_some(),egui::Button::new("Undo")).on_hover_text(format!("Undo {} (Ctrl+Z)",undo_label.unwrap_or_default())).clicked(){
+                    self.undo();
+                }
+                let redo_label = self.history.next_redo();
+                if ui.add_enabled(redo_label.is_some(),egui::Button::new("Redo")).on_hover_text(format!("Redo {} (Ctrl+Shift+Z)",redo_label.unwrap_or_default())).clicked(){
+                    self.redo();
+                }
+                ui.separator();
+                if ui.add(egui::Button::new("Image Creation")).clicked(){
+                    self.image_creation_windows.push(iris_image_creation::ImageCreator::new());
+                }
+                if ui.button("Movie Barcode").on_hover_text("Build a color strip from a folder of numbered frames").clicked(){
+                    self.sequence_windows.push(iris_sequence::SequenceWindow::new());
+                }
+                ui.separator();
+                ui.label("Project:");
+                ui.text_edit_singleline(&mut self.project_path);
+                if ui.button("Save").on_hover_text("Save open images, scan results and the palette").clicked(){
+                    self.save_project(PathBuf::from(self.project_path.trim()));
+                }
+                if ui.button("Open").on_hover_text("Replace the current session with a saved project.\nProject files can also be dropped onto the window").clicked(){
+                    self.load_project(PathBuf::from(self.project_path.trim()));
+                }
+            });
         });
+        if let Some(path) = self.file_browser.show(ctx) {
+            self.open_path(path);
+        }
+        if !self.errors.is_empty() {
+            egui::TopBottomPanel::bottom("Notifications").show(ctx,|ui| {
+                let mut error_to_remove = None;
+                egui::ScrollArea::vertical().max_height(120.0).show(ui,|ui| {
+                    for (index,err) in self.errors.iter().enumerate() {
+                        ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP),|ui| {
+                            if ui.small_button("x").on_hover_text("Dismiss").clicked(){
+                                error_to_remove = Some(index);
+                            }
+                            ui.colored_label(ui.visuals().error_fg_color,err);
+                        });
+                    }
+                });
+                if let Some(index) = error_to_remove {
+                    self.errors.remove(index);
+                }
+                if ui.button("Clear Errors").clicked() {
+                    self.errors.clear();
+                }
+            });
+        }
+        let palette_marks_before = iris_history::collect_marks(self.global_colors.iter());
         egui::SidePanel::left("ColorPanle").show(ctx,|ui| {
             ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP),|ui|{
                 if ui.button("Get Colors").on_hover_text("Copies every selected Color into Your Color Palet").clicked(){
+                    let before = self.global_colors.clone();
                     self.get_global_selected_colors();
+                    self.record_palette("Get colors",before);
                 }
                 if ui.button("Remove Selected Colors").clicked() {
+                    let before = self.global_colors.clone();
                     self.remove_selected_colors();
+                    self.record_palette("Remove colors",before);
                 }
             });
             ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP),|ui| {
@@ -1017,12 +1793,18 @@ impl eframe::App for MyEguiApp {
                     let r = (self.color_to_add[0] * 255.0).min(255.0) as u8; 
                     let g = (self.color_to_add[1] * 255.0).min(255.0) as u8; 
                     let b = (self.color_to_add[2] * 255.0).min(255.0) as u8; 
-                    let mut color = iris_color::AvarageRgb::from_rgb(Rgb::from([r,g,b]),[0,0]);
-                    color.generate_texture(ui);
-
+                    let color = iris_color::PaletteColor::from_rgb(Rgb::from([r,g,b]),[0,0]);
+                    let before = self.global_colors.clone();
                     self.global_colors.push(color);
+                    self.record_palette("Add color",before);
                 };
             });
+            ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP),|ui| {
+                if ui.button("Import").on_hover_text("Import a .gpl, .ase, .aco, .pal, .hex/.txt or PNG strip palette.\nPalette files can also be dropped onto the window").clicked(){
+                    self.import_palette(PathBuf::from(self.palette_import_path.trim()));
+                }
+                ui.text_edit_singleline(&mut self.palette_import_path);
+            });
             if ui.checkbox(&mut self.mark_every_color,"Select every color").clicked(){
                 for c in &mut self.global_colors.iter_mut(){
                    c.marked = self.mark_every_color; 
@@ -1032,8 +1814,9 @@ impl eframe::App for MyEguiApp {
                 let aw = ui.available_width();
                 egui::Grid::new("global_Colors").spacing(Vec2::new(0.0,3.0)).show(ui,|ui|{
                     let mut column_count = 0;
-                    for c in self.global_colors.iter_mut(){
-                        iris_color::color_display(ui,c);
+                    let ids = global_color_ids(&self.global_colors);
+                    for (c,id) in self.global_colors.iter_mut().zip(ids){
+                        iris_color::color_display(ui,c,id);
                         column_count += 1;
                         if column_count > (aw/(ui.available_width()+3.0)) as i32 {
                             ui.end_row();
@@ -1046,6 +1829,36 @@ impl eframe::App for MyEguiApp {
             if ui.button("Compare").on_hover_text("Compare selected colors").clicked(){
                 self.compare_window.push(ColorCompareWindow::new(self.get_selected_colors()));
             }
+            egui::CollapsingHeader::new("Copy as…").show(ui,|ui|{
+                egui::ComboBox::from_label("Format")
+                    .selected_text(self.export_format.to_string())
+                    .show_ui(ui,|ui|{
+                        for format in iris_palette::ExportFormat::ALL {
+                            ui.selectable_value(&mut self.export_format,format,format.to_string());
+                        }
+                    });
+                egui::ComboBox::from_label("Names")
+                    .selected_text(format!("{:?}",self.export_naming))
+                    .show_ui(ui,|ui|{
+                        ui.selectable_value(&mut self.export_naming,iris_palette::ExportNaming::ColorName,"Color Name");
+                        ui.selectable_value(&mut self.export_naming,iris_palette::ExportNaming::Index,"Index");
+                    });
+                ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP),|ui|{
+                    let any_selected = self.has_selected_colors();
+                    let selected = || -> Vec<Rgb<u8>> {self.get_selected_colors().iter().map(|c| c.to_rgb()).collect()};
+                    if ui.add_enabled(any_selected,egui::Button::new("Copy")).on_hover_text("Copy selected colors to the clipboard").clicked(){
+                        ui.ctx().copy_text(iris_palette::export_palette(&selected(),self.export_format,self.export_naming));
+                    }
+                    if ui.add_enabled(any_selected,egui::Button::new("Export")).on_hover_text("Save selected colors to ./created_images/").clicked(){
+                        let selected = selected();
+                        let file = format!("./created_images/{}",iris_palette::export_file_name(self.export_format,self.export_naming));
+                        let _ = std::fs::create_dir_all("./created_images");
+                        if let Err(err) = std::fs::write(&file,iris_palette::export_palette(&selected,self.export_format,self.export_naming)) {
+                            self.errors.push(format!("{}: {}",file,err));
+                        }
+                    }
+                });
+            });
             let mut compare_window_to_delete:Vec<usize> = vec![];
             for (index,w) in self.compare_window.iter_mut().enumerate() {
                 if w.window_open {
@@ -1075,112 +1888,81 @@ impl eframe::App for MyEguiApp {
                    image_creation_windows_to_remove.push(index); 
                 }
             }
+            for w in self.sequence_windows.iter_mut() {
+                w.show(ui.ctx());
+                self.errors.append(&mut w.errors);
+            }
+            self.sequence_windows.retain(|w| w.open);
+            let mut picked_colors = vec![];
+            for w in self.image_windows.iter_mut() {
+                for command in w.commands.drain(..) {
+                    self.history.push(command);
+                }
+                picked_colors.append(&mut w.picked_colors);
+                self.errors.append(&mut w.errors);
+            }
+            if !picked_colors.is_empty() {
+                let before = self.global_colors.clone();
+                self.global_colors.extend(picked_colors);
+                self.record_palette("Pick color",before);
+            }
             for index in image_window_to_remove{
                 self.image_windows.remove(index);
             }
         }); 
-        for color in self.global_colors.iter_mut() {
-            color.color_info_window_show(ctx);
+        let ids = global_color_ids(&self.global_colors);
+        for (color,id) in self.global_colors.iter_mut().zip(ids) {
+            color.color_info_window_show(ctx,id);
         }
+        let palette_marks_after = iris_history::collect_marks(self.global_colors.iter());
+        if palette_marks_before.len() == palette_marks_after.len() && palette_marks_before != palette_marks_after {
+            self.history.push(iris_history::Command::PaletteMarks{before:palette_marks_before,after:palette_marks_after});
+        }
+    }
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage,iris_file_browser::RECENT_FILES_KEY,&self.recent_files);
     }
     fn raw_input_hook(&mut self, _ctx: &egui::Context, raw_input: &mut egui::RawInput) {
         if raw_input.dropped_files.len() >= 1 {
             for file in raw_input.dropped_files.iter(){
-                if !self.has_image_window(file.path.clone().expect("No Path")){
-                    self.image_windows.push(ImageWindow::new(file.clone())); 
+                // browsers drop files without a path, only with their content
+                if let Some(path) = &file.path {
+                    self.open_path(path.clone());
+                }else if let Some(bytes) = &file.bytes {
+                    self.open_bytes(bytes,&file.name);
+                }else{
+                    self.errors.push(format!("{}: dropped file has neither a path nor data",file.name));
                 }
             }
         }
     }
 }
-#[derive(Clone)]
-struct MeanShiftCursor{
-    pub leeway:f32,
-    lab_pos:iris_color::OkLab,
-    rgb_color:[u8;3],
-    colors:HashSet<[u8;3]>,
-    radius:f32,
-}
-impl MeanShiftCursor {
-    pub fn new(color:[u8;3],radius:f32) -> Self{
-        let lab_pos = iris_color::OkLab::from_rgb(&Rgb::from(color));
-        MeanShiftCursor{
-            leeway:0.2,
-            lab_pos,
-            rgb_color:color,
-            colors:HashSet::new(),
-            radius,
-            
-        }
-    } 
-    pub fn update_rgb_color(&mut self){
-        self.rgb_color = self.lab_pos.to_rgb();
-    }
-    pub fn move_to_color_avarage(&mut self){
-        for c in self.colors.iter(){
-            let lab_b = iris_color::OkLab::from_rgb(&Rgb::from(*c));
-            self.lab_pos.add(&lab_b);
-        } 
-        self.lab_pos.diff(self.colors.len() as f32);
-        self.update_rgb_color();
-    }
-    pub fn add_color(&mut self,color:[u8;3]) -> bool{
-        if self.colors.contains(&color){
-            return false;
-        }
-        self.colors.insert(color);
-        true
-    }
-    pub fn inside_radius(&self,color:[u8;3]) -> bool {
-        let lab_b = iris_color::OkLab::from_rgb(&Rgb::from(color));
-        self.lab_pos.distance_to_lab(&lab_b) <= self.radius
-    }
-    pub fn is_same_as(&self,other:&Self) -> bool {
-        self.lab_pos.distance_to_lab(&other.lab_pos) <= (self.leeway + other.leeway)/2.0
-    }
-
-}
-impl Hash for MeanShiftCursor {
-    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        self.rgb_color.hash(state);
-    } 
-}
-
-impl PartialEq for MeanShiftCursor {
-   fn eq(&self, other: &Self) -> bool {
-        self.lab_pos.distance_to_lab(&other.lab_pos) <= (self.leeway + other.leeway)/2.0
-   } 
-}
-impl Eq for MeanShiftCursor {
-    
-}
-
 struct ColorCompareWindow {
     img:iris_image_creation::PieColorComp,
     texture:Option<egui::TextureHandle>,
-    colors:Vec<iris_color::AvarageRgb>,
+    colors:Vec<iris_color::PaletteColor>,
     id:usize,
     max_range:Option<f32>,
-    max_range_items:Option<[iris_color::AvarageRgb;2]>,
+    max_range_items:Option<[iris_color::PaletteColor;2]>,
     median_range:Option<f32>,
-    median_range_items:Option<[iris_color::AvarageRgb;2]>,
+    median_range_items:Option<[iris_color::PaletteColor;2]>,
     min_range:Option<f32>,
-    min_range_items:Option<[iris_color::AvarageRgb;2]>,
+    min_range_items:Option<[iris_color::PaletteColor;2]>,
     window_open:bool,
 }
 
 impl ColorCompareWindow {
-    fn new(colors:Vec<iris_color::AvarageRgb>) -> Self {
+    fn new(colors:Vec<iris_color::PaletteColor>) -> Self {
         WINDOW_ID.with(|thread_id|{
             let img = iris_image_creation::PieColorComp::new(colors.clone(),256);
             let texture:Option<egui::TextureHandle> = None;
             let id = thread_id.get();
             thread_id.set(id+1);
             let mut max_range:Option<f32> = Some(0_f32);
-            let mut max_range_items:Option<[iris_color::AvarageRgb;2]> = None;
+            let mut max_range_items:Option<[iris_color::PaletteColor;2]> = None;
             let mut min_range:Option<f32> = Some(f32::MAX);
-            let mut min_range_items:Option<[iris_color::AvarageRgb;2]> = None;
-            let mut all_range:Vec<(f32,[iris_color::AvarageRgb;2])> = vec![];
+            let mut min_range_items:Option<[iris_color::PaletteColor;2]> = None;
+            let mut all_range:Vec<(f32,[iris_color::PaletteColor;2])> = vec![];
             for combi in colors.iter().combinations(2) {
                 let lab_a = iris_color::OkLab::from_rgb(&Rgb::from(combi[0].to_rgb()));
                 let lab_b = iris_color::OkLab::from_rgb(&Rgb::from(combi[1].to_rgb()));
@@ -1196,7 +1978,7 @@ impl ColorCompareWindow {
                 all_range.push((range,[combi[0].clone(),combi[1].clone()]));
             }
             all_range.sort_by(|a,b| a.0.partial_cmp(&b.0).unwrap());
-            let mut median_range_items:Option<[iris_color::AvarageRgb; 2]> = None;
+            let mut median_range_items:Option<[iris_color::PaletteColor; 2]> = None;
             let mut median_range:Option<f32> = None;
 
             if all_range.len() >= 3 {